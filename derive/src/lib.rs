@@ -0,0 +1,211 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, punctuated::Punctuated, spanned::Spanned as _, Data,
+    DeriveInput, Error, Field, Fields, Ident, LitStr, Member, Result, Token, Type, WherePredicate,
+};
+
+/// derives `parse_pos::Spanned`
+///
+/// the span is the one of the field marked with `#[span]`, otherwise all fields
+/// not marked with `#[span(skip)]` get merged. enums do this for each variant.
+///
+/// type parameters used directly as the type of such a field get a `Spanned`
+/// bound, `#[span(bound = "T: Spanned")]` on the type replaces these bounds
+#[proc_macro_derive(Spanned, attributes(span))]
+pub fn derive_spanned(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// how a field takes part in the span
+#[derive(PartialEq)]
+enum Role {
+    /// the span of the whole node
+    Span,
+    Merge,
+    Skip,
+}
+
+fn role(field: &Field) -> Result<Role> {
+    let mut role = Role::Merge;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("span"))
+    {
+        if role != Role::Merge {
+            return Err(Error::new_spanned(attr, "duplicate `span` attribute"));
+        }
+        role = match &attr.meta {
+            syn::Meta::Path(_) => Role::Span,
+            _ => {
+                let mut skip = false;
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("skip") {
+                        skip = true;
+                        Ok(())
+                    } else {
+                        Err(meta.error("expected `skip`"))
+                    }
+                })?;
+                if skip {
+                    Role::Skip
+                } else {
+                    Role::Merge
+                }
+            }
+        };
+    }
+    Ok(role)
+}
+
+/// the body of `try_pos` over the bound fields, plus the types of the used fields
+fn body<'a>(fields: &'a Fields, bindings: &[Ident]) -> Result<(TokenStream, Vec<&'a Type>)> {
+    let mut span = None;
+    let mut merged = vec![];
+    for (field, binding) in fields.iter().zip(bindings) {
+        match role(field)? {
+            Role::Span if span.is_some() => {
+                return Err(Error::new_spanned(
+                    field,
+                    "only one field can be marked with `#[span]`",
+                ))
+            }
+            Role::Span => span = Some((field, binding)),
+            Role::Merge => merged.push((field, binding)),
+            Role::Skip => {}
+        }
+    }
+    if let Some((field, binding)) = span {
+        return Ok((
+            quote! { ::parse_pos::Spanned::try_pos(#binding) },
+            vec![&field.ty],
+        ));
+    }
+    let bindings = merged.iter().map(|(_, binding)| binding);
+    Ok((
+        quote! {
+            let mut pos: ::core::option::Option<::parse_pos::Position> = ::core::option::Option::None;
+            #(
+                if let ::core::option::Option::Some(other) = ::parse_pos::Spanned::try_pos(#bindings) {
+                    pos = ::core::option::Option::Some(match pos {
+                        ::core::option::Option::Some(pos) => pos.merge(&other),
+                        ::core::option::Option::None => other,
+                    });
+                }
+            )*
+            pos
+        },
+        merged.iter().map(|(field, _)| &field.ty).collect(),
+    ))
+}
+
+/// binds the fields to `__0`, `__1`, ... in a pattern
+fn pattern(fields: &Fields) -> (TokenStream, Vec<Ident>) {
+    let bindings = (0..fields.len())
+        .map(|i| format_ident!("__{}", i))
+        .collect::<Vec<_>>();
+    let members = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(i.into()),
+        });
+    let pattern = match fields {
+        Fields::Unit => quote! {},
+        _ => quote! { { #(#members: #bindings),* } },
+    };
+    (pattern, bindings)
+}
+
+/// the predicates of a `#[span(bound = "...")]` on the type, if there is one
+fn bound(input: &DeriveInput) -> Result<Option<Vec<WherePredicate>>> {
+    let mut bound = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("span"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("bound") {
+                let predicates: LitStr = meta.value()?.parse()?;
+                let predicates = predicates
+                    .parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)?;
+                bound = Some(predicates.into_iter().collect());
+                Ok(())
+            } else {
+                Err(meta.error("expected `bound`"))
+            }
+        })?;
+    }
+    Ok(bound)
+}
+
+fn expand(mut input: DeriveInput) -> Result<TokenStream> {
+    let name = &input.ident;
+    let (arms, types) = match &input.data {
+        Data::Struct(data) => {
+            let (pattern, bindings) = pattern(&data.fields);
+            let (body, types) = body(&data.fields, &bindings)?;
+            (vec![quote! { #name #pattern => { #body } }], types)
+        }
+        Data::Enum(data) => {
+            let mut arms = vec![];
+            let mut types = vec![];
+            for variant in &data.variants {
+                let ident = &variant.ident;
+                let (pattern, bindings) = pattern(&variant.fields);
+                let (body, used) = body(&variant.fields, &bindings)?;
+                arms.push(quote! { #name::#ident #pattern => { #body } });
+                types.extend(used);
+            }
+            (arms, types)
+        }
+        Data::Union(data) => {
+            return Err(Error::new(
+                data.union_token.span(),
+                "`Spanned` can't be derived for unions",
+            ))
+        }
+    };
+    // bounding whole field types could make impls of mutually recursive
+    // types depend on each other, so only bare type parameters get bounds
+    let predicates = match bound(&input)? {
+        Some(predicates) => predicates,
+        None => input
+            .generics
+            .type_params()
+            .map(|param| &param.ident)
+            .filter(|param| {
+                types
+                    .iter()
+                    .any(|ty| matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident(*param)))
+            })
+            .map(|param| parse_quote! { #param: ::parse_pos::Spanned })
+            .collect(),
+    };
+    input
+        .generics
+        .make_where_clause()
+        .predicates
+        .extend(predicates);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        #[automatically_derived]
+        impl #impl_generics ::parse_pos::Spanned for #name #ty_generics #where_clause {
+            fn pos(&self) -> ::parse_pos::Position {
+                ::parse_pos::Spanned::try_pos(self).unwrap_or_default()
+            }
+            #[allow(unused_variables)]
+            fn try_pos(&self) -> ::core::option::Option<::parse_pos::Position> {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    })
+}
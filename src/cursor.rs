@@ -0,0 +1,201 @@
+use crate::{LineEndings, Located, Position, SourceFile};
+
+/// character cursor over source text which keeps track of its line and column,
+/// for hand-written lexers
+#[derive(Debug, Clone)]
+pub struct Cursor<'a> {
+    text: &'a str,
+    line_endings: LineEndings,
+    offset: usize,
+    ln: usize,
+    col: usize,
+}
+/// a location of a `Cursor` to build spans from, made by `Cursor::mark`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Mark {
+    offset: usize,
+    ln: usize,
+    col: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self::with_line_endings(text, LineEndings::default())
+    }
+    /// creates a `Cursor` which starts a new line after `line_endings`
+    pub fn with_line_endings(text: &'a str, line_endings: LineEndings) -> Self {
+        Self {
+            text,
+            line_endings,
+            offset: 0,
+            ln: 0,
+            col: 0,
+        }
+    }
+    /// creates a `Cursor` over the text of `source` using its line endings,
+    /// so its spans line up with the ones `source` computes
+    pub fn for_source(source: &'a SourceFile) -> Self {
+        Self::with_line_endings(source.text(), source.line_endings())
+    }
+    /// the whole text the cursor moves over
+    pub fn text(&self) -> &'a str {
+        self.text
+    }
+    /// the text which hasn't been advanced over yet
+    pub fn rest(&self) -> &'a str {
+        &self.text[self.offset..]
+    }
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+    pub fn ln(&self) -> usize {
+        self.ln
+    }
+    /// byte column on the current line
+    pub fn col(&self) -> usize {
+        self.col
+    }
+    pub fn is_eof(&self) -> bool {
+        self.offset == self.text.len()
+    }
+    /// the next character without advancing
+    pub fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+    /// the character `n` characters ahead without advancing, `peek_nth(0)` is `peek()`
+    pub fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+    /// advances over the next character and returns it
+    pub fn advance(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        if self.line_endings.ends_line(c, self.peek()) {
+            self.ln += 1;
+            self.col = 0;
+        } else {
+            self.col += c.len_utf8();
+        }
+        Some(c)
+    }
+    /// advances over the next character if it's `c`
+    pub fn eat(&mut self, c: char) -> bool {
+        self.eat_if(|next| next == c)
+    }
+    /// advances over the next character if it matches `pred`
+    pub fn eat_if(&mut self, pred: impl FnOnce(char) -> bool) -> bool {
+        match self.peek() {
+            Some(c) if pred(c) => {
+                self.advance();
+                true
+            }
+            _ => false,
+        }
+    }
+    /// advances while the characters match `pred` and returns what was advanced over
+    pub fn eat_while(&mut self, mut pred: impl FnMut(char) -> bool) -> &'a str {
+        let start = self.offset;
+        while self.eat_if(&mut pred) {}
+        &self.text[start..self.offset]
+    }
+    /// the current location, to later get the span advanced over since
+    pub fn mark(&self) -> Mark {
+        Mark {
+            offset: self.offset,
+            ln: self.ln,
+            col: self.col,
+        }
+    }
+    /// moves the cursor back (or forward) to `mark`
+    pub fn reset(&mut self, mark: Mark) {
+        self.offset = mark.offset;
+        self.ln = mark.ln;
+        self.col = mark.col;
+    }
+    /// the span from `mark` to the current location, with byte offsets
+    pub fn span_since(&self, mark: Mark) -> Position {
+        Position::new(mark.ln..self.ln, mark.col..self.col).with_offset(mark.offset..self.offset)
+    }
+    /// the text from `mark` to the current location
+    pub fn slice_since(&self, mark: Mark) -> &'a str {
+        &self.text[mark.offset..self.offset]
+    }
+    /// `value` located at the span from `mark` to the current location
+    pub fn located<T>(&self, mark: Mark, value: T) -> Located<T> {
+        Located::new(value, self.span_since(mark))
+    }
+}
+impl Mark {
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+    pub fn ln(&self) -> usize {
+        self.ln
+    }
+    pub fn col(&self) -> usize {
+        self.col
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn lexing() {
+        let mut cursor = Cursor::new("let \u{e9}\n  = 42");
+        let mut tokens = vec![];
+        loop {
+            cursor.eat_while(char::is_whitespace);
+            let mark = cursor.mark();
+            let Some(c) = cursor.peek() else {
+                break;
+            };
+            if c.is_alphabetic() {
+                let word = cursor.eat_while(char::is_alphanumeric);
+                tokens.push(cursor.located(mark, word.to_string()));
+            } else if c.is_ascii_digit() {
+                cursor.eat_while(|c| c.is_ascii_digit());
+                tokens.push(cursor.located(mark, cursor.slice_since(mark).to_string()));
+            } else {
+                cursor.advance();
+                tokens.push(cursor.located(mark, c.to_string()));
+            }
+        }
+        let positions = tokens
+            .iter()
+            .map(|token| token.pos.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            ["let", "\u{e9}", "=", "42"].map(|value| Located::new_default(value.to_string()))
+        );
+        assert_eq!(positions[1], Position::new(0..0, 4..6).with_offset(4..6));
+        assert_eq!(positions[2], Position::new(1..1, 2..3).with_offset(9..10));
+        assert_eq!(positions[3], Position::new(1..1, 4..6).with_offset(11..13));
+        assert_eq!(cursor.peek_nth(0), None);
+    }
+    #[test]
+    fn line_endings() {
+        let text = "a\r\nb\rc";
+        let mut cursor = Cursor::new(text);
+        cursor.eat_while(|c| c != 'c');
+        let source = SourceFile::new(text);
+        assert_eq!(
+            (cursor.ln(), cursor.col()),
+            source.location(cursor.offset())
+        );
+        let mut cursor = Cursor::with_line_endings(text, LineEndings::Lf);
+        cursor.eat_while(|c| c != 'c');
+        assert_eq!((cursor.ln(), cursor.col()), (1, 2));
+        let source = SourceFile::with_line_endings(text, LineEndings::Lf);
+        let mut cursor = Cursor::for_source(&source);
+        cursor.eat_while(|c| c != 'b');
+        let mark = cursor.mark();
+        cursor.eat_while(|_| true);
+        let pos = cursor.span_since(mark);
+        assert_eq!(pos, source.position(3..6));
+        assert_eq!(pos.slice_in(&source), Some("b\rc"));
+        let pos = Position::new(pos.ln, pos.col);
+        assert_eq!(pos.slice_in(&source), Some("b\rc"));
+    }
+}
@@ -0,0 +1,381 @@
+use std::{
+    fmt::{Display, Write},
+    io::{self, IsTerminal},
+};
+
+use crate::{
+    render::{self, Style},
+    FileId, Position, RenderConfig, SourceFile, SourceMap,
+};
+
+/// how severe a `Diagnostic` is
+///
+/// with the `serde` feature it's (de)serialized as `"error"`, `"warning"`, `"note"` or `"help"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+/// whether a `Label` marks the main cause or additional context
+///
+/// with the `serde` feature it's (de)serialized as `"primary"` or `"secondary"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LabelStyle {
+    Primary,
+    Secondary,
+}
+/// a `Position` in a file with a message attached to it
+///
+/// with the `serde` feature it's (de)serialized as
+/// `{"style": LabelStyle, "file": FileId, "pos": Position, "message": "..."}`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Label {
+    pub style: LabelStyle,
+    pub file: FileId,
+    pub pos: Position,
+    pub message: String,
+}
+/// replacing the text at `pos` in `file` with `text`
+///
+/// with the `serde` feature it's (de)serialized as
+/// `{"file": FileId, "pos": Position, "text": "..."}`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Replacement {
+    pub file: FileId,
+    pub pos: Position,
+    pub text: String,
+}
+/// a proposed fix made up of replacements which are applied together
+///
+/// with the `serde` feature it's (de)serialized as
+/// `{"message": "...", "replacements": [Replacement]}`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Suggestion {
+    pub message: String,
+    pub replacements: Vec<Replacement>,
+}
+/// `Display` adapter for a `Diagnostic`, made by `Diagnostic::display`
+pub struct DisplayDiagnostic<'a> {
+    diagnostic: &'a Diagnostic,
+    map: &'a SourceMap,
+    config: RenderConfig,
+}
+/// a message about the source with labelled positions, notes and help
+///
+/// with the `serde` feature it's (de)serialized with the field names as keys,
+/// `code` being `null` if there is none
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+            Self::Note => write!(f, "note"),
+            Self::Help => write!(f, "help"),
+        }
+    }
+}
+impl LabelStyle {
+    pub fn is_primary(self) -> bool {
+        self == Self::Primary
+    }
+}
+impl Label {
+    pub fn new(style: LabelStyle, file: FileId, pos: Position) -> Self {
+        Self {
+            style,
+            file,
+            pos,
+            message: String::new(),
+        }
+    }
+    pub fn primary(file: FileId, pos: Position) -> Self {
+        Self::new(LabelStyle::Primary, file, pos)
+    }
+    pub fn secondary(file: FileId, pos: Position) -> Self {
+        Self::new(LabelStyle::Secondary, file, pos)
+    }
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+}
+impl Replacement {
+    pub fn new(file: FileId, pos: Position, text: impl Into<String>) -> Self {
+        Self {
+            file,
+            pos,
+            text: text.into(),
+        }
+    }
+}
+impl Suggestion {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            replacements: vec![],
+        }
+    }
+    pub fn with_replacement(
+        mut self,
+        file: FileId,
+        pos: Position,
+        text: impl Into<String>,
+    ) -> Self {
+        self.replacements.push(Replacement::new(file, pos, text));
+        self
+    }
+}
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: vec![],
+            notes: vec![],
+            help: vec![],
+            suggestions: vec![],
+        }
+    }
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+    pub fn note(message: impl Into<String>) -> Self {
+        Self::new(Severity::Note, message)
+    }
+    pub fn help(message: impl Into<String>) -> Self {
+        Self::new(Severity::Help, message)
+    }
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+    pub fn with_labels(mut self, labels: impl IntoIterator<Item = Label>) -> Self {
+        self.labels.extend(labels);
+        self
+    }
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.suggestions.push(suggestion);
+        self
+    }
+    /// the first primary label, or the first label if there is no primary one
+    pub fn primary_label(&self) -> Option<&Label> {
+        self.labels
+            .iter()
+            .find(|label| label.style == LabelStyle::Primary)
+            .or(self.labels.first())
+    }
+    /// renders the diagnostic with snippets of the labelled files in `map`
+    pub fn render(&self, f: &mut impl Write, map: &SourceMap) -> std::fmt::Result {
+        self.render_with(f, map, &RenderConfig::default())
+    }
+    /// like `render` but rendered according to `config`,
+    /// `ColorChoice::Auto` colors if stderr is a terminal
+    pub fn render_with(
+        &self,
+        f: &mut impl Write,
+        map: &SourceMap,
+        config: &RenderConfig,
+    ) -> std::fmt::Result {
+        self.render_resolved(f, map, &config.resolved(io::stderr().is_terminal()))
+    }
+    fn render_resolved(
+        &self,
+        f: &mut impl Write,
+        map: &SourceMap,
+        config: &RenderConfig,
+    ) -> std::fmt::Result {
+        let severity = Style::Severity(self.severity);
+        let header = match &self.code {
+            Some(code) => format!("{}[{code}]", self.severity),
+            None => self.severity.to_string(),
+        };
+        writeln!(
+            f,
+            "{}{}",
+            config.paint(severity, header),
+            config.paint(Style::Emphasis, format!(": {}", self.message))
+        )?;
+        let mut labels = self.labels.iter().collect::<Vec<&Label>>();
+        labels.sort_by_key(|label| label.style != LabelStyle::Primary);
+        let mut files: Vec<(FileId, Vec<&Label>)> = vec![];
+        for label in labels {
+            match files.iter_mut().find(|(file, _)| *file == label.file) {
+                Some((_, labels)) => labels.push(label),
+                None => files.push((label.file, vec![label])),
+            }
+        }
+        let gutter = config.gutter(
+            self.labels
+                .iter()
+                .map(|label| match map.get(label.file) {
+                    Some(source) => config.last_shown(source, label.pos.ln.end),
+                    None => label.pos.ln.end,
+                })
+                .max()
+                .unwrap_or_default(),
+        );
+        for (idx, (file, labels)) in files.into_iter().enumerate() {
+            let Some(source) = map.get(file) else {
+                writeln!(f, "{:gutter$} ... code snippet unavailable ...", "")?;
+                continue;
+            };
+            let arrow = config.paint(Style::Gutter, if idx == 0 { "-->" } else { ":::" });
+            let (ln, col) = location(source, &labels[0].pos);
+            match source.path() {
+                Some(path) => writeln!(f, "{:gutter$}{arrow} {}:{ln}:{col}", "", path.display())?,
+                None => writeln!(f, "{:gutter$}{arrow} {ln}:{col}", "")?,
+            }
+            if config.separator {
+                writeln!(f, "{}", config.bar(gutter))?;
+            }
+            let marks = labels
+                .iter()
+                .map(|label| {
+                    let (marker, style) = match label.style {
+                        LabelStyle::Primary => (config.primary_marker, severity),
+                        LabelStyle::Secondary => (config.secondary_marker, Style::Secondary),
+                    };
+                    render::Mark {
+                        pos: &label.pos,
+                        marker,
+                        style,
+                        label: &label.message,
+                    }
+                })
+                .collect::<Vec<_>>();
+            render::snippet(f, source, config, gutter, &marks)?;
+        }
+        let trailing = self.notes.len() + self.help.len() + self.suggestions.len();
+        if config.separator && trailing > 0 {
+            writeln!(f, "{}", config.bar(gutter))?;
+        }
+        for note in &self.notes {
+            let note_label = config.paint(Style::Emphasis, "= note:");
+            writeln!(f, "{:gutter$} {note_label} {note}", "")?;
+        }
+        for help in &self.help {
+            let help_label = config.paint(Style::Emphasis, "= help:");
+            writeln!(f, "{:gutter$} {help_label} {help}", "")?;
+        }
+        for suggestion in &self.suggestions {
+            let help_label = config.paint(Style::Emphasis, "= help:");
+            match suggestion.replacements.as_slice() {
+                [replacement] => writeln!(
+                    f,
+                    "{:gutter$} {help_label} {}: `{}`",
+                    "", suggestion.message, replacement.text
+                )?,
+                _ => writeln!(f, "{:gutter$} {help_label} {}", "", suggestion.message)?,
+            }
+        }
+        Ok(())
+    }
+    /// like `render` but writes to an `io::Write` like stderr
+    pub fn write(&self, w: &mut impl io::Write, map: &SourceMap) -> io::Result<()> {
+        render::write_io(w, |f| self.render(f, map))
+    }
+    /// like `render_with` but writes to an `io::Write` like stderr,
+    /// `ColorChoice::Auto` colors if `w` is a terminal
+    pub fn write_with<W: io::Write + IsTerminal>(
+        &self,
+        w: &mut W,
+        map: &SourceMap,
+        config: &RenderConfig,
+    ) -> io::Result<()> {
+        let config = config.resolved(w.is_terminal());
+        render::write_io(w, |f| self.render_resolved(f, map, &config))
+    }
+    /// adapter implementing `Display` for the rendered diagnostic,
+    /// for using it in `format!` or `eprintln!`
+    pub fn display<'a>(&'a self, map: &'a SourceMap) -> DisplayDiagnostic<'a> {
+        DisplayDiagnostic {
+            diagnostic: self,
+            map,
+            config: RenderConfig::default(),
+        }
+    }
+}
+
+impl DisplayDiagnostic<'_> {
+    pub fn with_config(mut self, config: RenderConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+impl Display for DisplayDiagnostic<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.diagnostic.render_with(f, self.map, &self.config)
+    }
+}
+
+/// one based line and character column where `pos` starts
+fn location(source: &SourceFile, pos: &Position) -> (usize, usize) {
+    let col = source
+        .line(pos.ln.start)
+        .and_then(|line| line.get(..pos.col.start))
+        .map_or(pos.col.start, |before| before.chars().count());
+    (pos.ln.start + 1, col + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    #[test]
+    fn render() {
+        let mut map = SourceMap::new();
+        let file = map.add(Path::new("main.txt").into(), "let x = 1;\nlet y = x + z;\n");
+        let diagnostic = Diagnostic::error("cannot find value `z`")
+            .with_code("E0425")
+            .with_label(
+                Label::secondary(file, Position::new(0..0, 4..5)).with_message("similar name"),
+            )
+            .with_label(Label::primary(file, Position::new(1..1, 12..13)).with_message("not found"))
+            .with_help("did you mean `x`?");
+        let mut display = String::new();
+        diagnostic.render(&mut display, &map).unwrap();
+        assert_eq!(diagnostic.display(&map).to_string(), display);
+        assert_eq!(
+            display,
+            "error[E0425]: cannot find value `z`\n --> main.txt:2:13\n  |\n1 | let x = 1;\n  |     - similar name\n2 | let y = x + z;\n  |             ^ not found\n  |\n  = help: did you mean `x`?\n"
+        );
+    }
+}
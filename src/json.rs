@@ -0,0 +1,284 @@
+use std::fmt::{Display, Write};
+
+use crate::{ColumnUnit, Diagnostic, FileId, Position, RenderConfig, Severity, SourceMap};
+
+/// minimal JSON value written by the machine readable emitters
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(usize),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Self::Number(value)
+    }
+}
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(value: Vec<T>) -> Self {
+        Self::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Number(value) => write!(f, "{value}"),
+            Self::String(value) => write_string(f, value),
+            Self::Array(values) => {
+                f.write_char('[')?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_char(']')
+            }
+            Self::Object(fields) => {
+                f.write_char('{')?;
+                for (idx, (key, value)) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+fn write_string(f: &mut impl Write, value: &str) -> std::fmt::Result {
+    f.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl Diagnostic {
+    /// the diagnostic as a single line JSON object, laid out like the
+    /// diagnostics of rustc's `--error-format=json`
+    pub fn to_json(&self, map: &SourceMap) -> String {
+        self.to_json_with(map, &RenderConfig::default())
+    }
+    /// like `to_json` but the `rendered` field is rendered according to `config`
+    pub fn to_json_with(&self, map: &SourceMap, config: &RenderConfig) -> String {
+        let spans = self
+            .labels
+            .iter()
+            .map(|label| {
+                let message = (!label.message.is_empty()).then_some(label.message.as_str());
+                span(
+                    map,
+                    label.file,
+                    &label.pos,
+                    label.style.is_primary(),
+                    message,
+                    None,
+                )
+            })
+            .collect();
+        let mut children = vec![];
+        for note in &self.notes {
+            children.push(child(Severity::Note, note, vec![]));
+        }
+        for help in &self.help {
+            children.push(child(Severity::Help, help, vec![]));
+        }
+        for suggestion in &self.suggestions {
+            let spans = suggestion
+                .replacements
+                .iter()
+                .map(|replacement| {
+                    let text = Some(replacement.text.as_str());
+                    span(map, replacement.file, &replacement.pos, true, None, text)
+                })
+                .collect();
+            children.push(child(Severity::Help, &suggestion.message, spans));
+        }
+        // the json isn't a terminal, whatever stderr is
+        let config = config.resolved(false);
+        let mut rendered = String::new();
+        let rendered = self
+            .render_with(&mut rendered, map, &config)
+            .is_ok()
+            .then_some(rendered);
+        Json::Object(vec![
+            ("$message_type", "diagnostic".into()),
+            ("message", self.message.as_str().into()),
+            (
+                "code",
+                match &self.code {
+                    Some(code) => Json::Object(vec![
+                        ("code", code.as_str().into()),
+                        ("explanation", Json::Null),
+                    ]),
+                    None => Json::Null,
+                },
+            ),
+            ("level", self.severity.to_string().into()),
+            ("spans", Json::Array(spans)),
+            ("children", Json::Array(children)),
+            ("rendered", rendered.into()),
+        ])
+        .to_string()
+    }
+}
+
+/// a child diagnostic without code, children and rendering, like rustc's notes
+fn child(severity: Severity, message: &str, spans: Vec<Json>) -> Json {
+    Json::Object(vec![
+        ("message", message.into()),
+        ("code", Json::Null),
+        ("level", severity.to_string().into()),
+        ("spans", Json::Array(spans)),
+        ("children", Json::Array(vec![])),
+        ("rendered", Json::Null),
+    ])
+}
+/// a span with one based lines and character columns, the covered lines and
+/// their highlighted columns
+fn span(
+    map: &SourceMap,
+    file: FileId,
+    pos: &Position,
+    is_primary: bool,
+    label: Option<&str>,
+    replacement: Option<&str>,
+) -> Json {
+    let source = map.get(file);
+    let column = |ln: usize, col: usize| {
+        source
+            .and_then(|source| source.column(ln, col, ColumnUnit::Chars))
+            .unwrap_or(col)
+            + 1
+    };
+    let bytes = source
+        .and_then(|source| source.byte_range(pos))
+        .or(pos.offset.clone())
+        .unwrap_or_default();
+    let text = (pos.ln.start..=pos.ln.end)
+        .filter_map(|ln| {
+            let line = source?.line(ln)?;
+            let start = if ln == pos.ln.start {
+                column(ln, pos.col.start)
+            } else {
+                1
+            };
+            let end = if ln == pos.ln.end {
+                column(ln, pos.col.end)
+            } else {
+                line.chars().count() + 1
+            };
+            Some(Json::Object(vec![
+                ("text", line.into()),
+                ("highlight_start", start.into()),
+                ("highlight_end", end.into()),
+            ]))
+        })
+        .collect();
+    let file_name = source.and_then(|source| source.path()).map_or_else(
+        || "<unknown>".to_string(),
+        |path| path.display().to_string(),
+    );
+    Json::Object(vec![
+        ("file_name", file_name.into()),
+        ("byte_start", bytes.start.into()),
+        ("byte_end", bytes.end.into()),
+        ("line_start", (pos.ln.start + 1).into()),
+        ("line_end", (pos.ln.end + 1).into()),
+        ("column_start", column(pos.ln.start, pos.col.start).into()),
+        ("column_end", column(pos.ln.end, pos.col.end).into()),
+        ("is_primary", is_primary.into()),
+        ("text", Json::Array(text)),
+        ("label", label.into()),
+        ("suggested_replacement", replacement.into()),
+        (
+            "suggestion_applicability",
+            replacement.map(|_| "Unspecified").into(),
+        ),
+        ("expansion", Json::Null),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{Label, Suggestion};
+
+    use super::*;
+    #[test]
+    fn escapes() {
+        let json = Json::Object(vec![
+            ("text", "say \"hi\"\n\t\\ \u{1}".into()),
+            ("list", vec![Some(1), None].into()),
+        ]);
+        assert_eq!(
+            json.to_string(),
+            r#"{"text":"say \"hi\"\n\t\\ \u0001","list":[1,null]}"#
+        );
+    }
+    #[test]
+    fn diagnostic() {
+        let mut map = SourceMap::new();
+        let file = map.add(Path::new("main.txt").into(), "let \u{e9} = y;\n");
+        let pos = Position::new(0..0, 9..10);
+        let diagnostic = Diagnostic::error("cannot find value `y`")
+            .with_code("E0425")
+            .with_label(Label::primary(file, pos.clone()).with_message("not found"))
+            .with_note("values must be declared")
+            .with_suggestion(Suggestion::new("use").with_replacement(file, pos, "\u{e9}"));
+        let json: serde_json::Value = serde_json::from_str(&diagnostic.to_json(&map)).unwrap();
+        assert_eq!(json["level"], "error");
+        assert_eq!(json["code"]["code"], "E0425");
+        let span = &json["spans"][0];
+        assert_eq!(span["file_name"], "main.txt");
+        assert_eq!(
+            (&span["byte_start"], &span["byte_end"]),
+            (&9.into(), &10.into())
+        );
+        assert_eq!(span["column_start"], 9);
+        assert_eq!(span["text"][0]["highlight_end"], 10);
+        assert_eq!(span["label"], "not found");
+        assert_eq!(json["children"][0]["level"], "note");
+        let replacement = &json["children"][1]["spans"][0];
+        assert_eq!(replacement["suggested_replacement"], "\u{e9}");
+        assert_eq!(json["rendered"], diagnostic.display(&map).to_string());
+    }
+}
@@ -1,47 +1,216 @@
+mod cursor;
+mod diagnostic;
+mod json;
+mod lsp;
+mod render;
+mod sarif;
+mod source;
+mod spanned;
+
 use std::{
+    borrow::Cow,
     fmt::{Debug, Display, Write},
     hash::Hash,
-    ops::Range,
+    io::{self, IsTerminal},
+    ops::{Deref, DerefMut, Range},
     path::Path,
 };
 
+pub use cursor::{Cursor, Mark};
+pub use diagnostic::{
+    Diagnostic, DisplayDiagnostic, Label, LabelStyle, Replacement, Severity, Suggestion,
+};
+pub use lsp::{LspPosition, LspRange, PositionEncoding};
+/// `#[derive(Spanned)]`, with the `derive` feature
+///
+/// the span is the one of the field marked with `#[span]`, otherwise all fields
+/// not marked with `#[span(skip)]` get merged. enums do this for each variant.
+/// `#[span(bound = "...")]` on the type sets the bounds of the impl
+#[cfg(feature = "derive")]
+pub use parse_pos_derive::Spanned;
+pub use render::{ColorChoice, RenderConfig, Snippet};
+pub use sarif::Sarif;
+pub use source::{ColumnUnit, FileId, LineEndings, SourceFile, SourceMap};
+pub use spanned::Spanned;
+
+// lets the derive macro refer to `::parse_pos` inside this crate too
+extern crate self as parse_pos;
+
 /// position span
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+///
+/// with the `serde` feature it's (de)serialized as
+/// `{"ln": {"start": 0, "end": 0}, "col": {"start": 0, "end": 0}, "offset": null}`,
+/// where `offset` is `null` or another `{"start", "end"}` range
+///
+/// equality and hashing only look at lines and columns, `offset` is a cache
+/// and gets ignored
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Position {
     pub ln: Range<usize>,
     pub col: Range<usize>,
+    /// absolute byte offsets into the source, if known
+    pub offset: Option<Range<usize>>,
 }
 /// `T` with a `Position` which is transparent in most cases
+///
+/// with the `serde` feature it's (de)serialized as `{"value": T, "pos": Position}`
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Located<T> {
     pub value: T,
     pub pos: Position,
 }
 /// `T` with a `Position` and a `Path`
-pub struct PathLocated<T> {
+///
+/// the path is owned unless it's a view made by `as_ref` or `as_mut`,
+/// which borrow it as `&Path`
+///
+/// with the `serde` feature it's (de)serialized as
+/// `{"value": T, "pos": Position, "path": "path/to/file"}`
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathLocated<T, P = Box<Path>> {
     pub value: T,
     pub pos: Position,
-    pub path: Box<Path>,
+    pub path: P,
 }
+/// `T` with a `Position` and the `FileId` of its file in a `SourceMap`
+///
+/// with the `serde` feature it's (de)serialized as
+/// `{"value": T, "pos": Position, "file": 0}`
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileLocated<T> {
+    pub value: T,
+    pub pos: Position,
+    pub file: FileId,
+}
+/// compares, hashes and debug prints located values including their
+/// positions (and paths or files), where they normally only use the inner value
+///
+/// `Strict(a.as_ref()) == Strict(b.as_ref())` compares borrowed values
+#[derive(Clone, Copy, Default)]
+pub struct Strict<T>(pub T);
 
+impl PartialEq for Position {
+    fn eq(&self, other: &Self) -> bool {
+        self.ln == other.ln && self.col == other.col
+    }
+}
+impl Eq for Position {}
+impl Hash for Position {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.ln.hash(state);
+        self.col.hash(state);
+    }
+}
 impl Position {
     pub fn new(ln: Range<usize>, col: Range<usize>) -> Self {
-        Self { ln, col }
+        Self {
+            ln,
+            col,
+            offset: None,
+        }
+    }
+    /// zero width position pointing between characters, before column `col`
+    pub fn point(ln: usize, col: usize) -> Self {
+        Self::new(ln..ln, col..col)
+    }
+    /// whether the position is zero width
+    pub fn is_point(&self) -> bool {
+        self.ln.start == self.ln.end && self.col.start == self.col.end
+    }
+    /// creates a `Position` from absolute byte offsets into `content`
+    ///
+    /// lines and columns are computed from the offsets, columns are byte based.
+    /// lines are split with `LineEndings::default()`, `SourceFile::position`
+    /// uses the line endings of the file instead
+    pub fn from_offset(offset: Range<usize>, content: &str) -> Self {
+        let (ln_start, col_start) = line_col(content, offset.start);
+        let (ln_end, col_end) = line_col(content, offset.end);
+        Self {
+            ln: ln_start..ln_end,
+            col: col_start..col_end,
+            offset: Some(offset),
+        }
+    }
+    /// attaches absolute byte offsets to the position
+    pub fn with_offset(mut self, offset: Range<usize>) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+    /// the absolute byte offsets covered in `content`,
+    /// computed from lines and columns if they aren't stored
+    ///
+    /// lines are split with `LineEndings::default()`, `SourceFile::byte_range`
+    /// uses the line endings of the file instead
+    pub fn byte_range(&self, content: &str) -> Option<Range<usize>> {
+        if let Some(offset) = &self.offset {
+            return Some(offset.clone());
+        }
+        let start = line_offset(content, self.ln.start, self.col.start)?;
+        let end = line_offset(content, self.ln.end, self.col.end)?;
+        (start <= end && end <= content.len()).then_some(start..end)
+    }
+    /// the exact part of `content` covered by the position
+    pub fn slice<'a>(&self, content: &'a str) -> Option<&'a str> {
+        content.get(self.byte_range(content)?)
+    }
+    /// like `slice` but uses the line index and line endings of `source`
+    pub fn slice_in<'a>(&self, source: &'a SourceFile) -> Option<&'a str> {
+        source.text().get(source.byte_range(self)?)
     }
     /// extends it's span by another span
+    ///
+    /// start and end are compared as (line, column) pairs, so the start
+    /// column always belongs to the start line and the end column to the end line
     pub fn extend(&mut self, other: &Self) {
-        if self.ln.start > other.ln.start {
+        if (other.ln.start, other.col.start) < (self.ln.start, self.col.start) {
             self.ln.start = other.ln.start;
-        }
-        if self.ln.end < other.ln.end {
-            self.ln.end = other.ln.end;
-        }
-        if self.col.start > other.col.start {
             self.col.start = other.col.start;
         }
-        if self.col.end < other.col.end {
+        if (other.ln.end, other.col.end) > (self.ln.end, self.col.end) {
+            self.ln.end = other.ln.end;
             self.col.end = other.col.end;
         }
+        self.offset = match (self.offset.take(), &other.offset) {
+            (Some(offset), Some(other)) => {
+                Some(offset.start.min(other.start)..offset.end.max(other.end))
+            }
+            _ => None,
+        };
+    }
+    /// the span covering both spans
+    pub fn merge(&self, other: &Self) -> Self {
+        let mut pos = self.clone();
+        pos.extend(other);
+        pos
+    }
+}
+/// line and byte column of `offset` in `content`
+fn line_col(content: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(content.len());
+    let mut ln = 0;
+    let mut start = 0;
+    for line_break in LineEndings::default().breaks(content) {
+        if line_break.end > offset {
+            break;
+        }
+        ln += 1;
+        start = line_break.end;
     }
+    (ln, offset - start)
+}
+/// byte offset of the byte column `col` on line `ln` in `content`,
+/// `None` if the column is past the line and its terminator
+fn line_offset(content: &str, ln: usize, col: usize) -> Option<usize> {
+    let mut breaks = LineEndings::default().breaks(content);
+    let start = match ln {
+        0 => 0,
+        _ => breaks.nth(ln - 1)?.end,
+    };
+    let end = breaks
+        .next()
+        .map_or(content.len(), |line_break| line_break.end);
+    (col <= end - start).then_some(start + col)
 }
 impl<T> Located<T> {
     pub fn new(value: T, pos: Position) -> Self {
@@ -55,12 +224,47 @@ impl<T> Located<T> {
         }
     }
     /// maps the inner value to a different value
-    pub fn map<U, F: Fn(T) -> U>(self, f: F) -> Located<U> {
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Located<U> {
         Located {
             value: f(self.value),
             pos: self.pos,
         }
     }
+    /// maps the inner value with a fallible function, the error is located
+    /// at the same position
+    pub fn try_map<U, E, F: FnOnce(T) -> Result<U, E>>(
+        self,
+        f: F,
+    ) -> Result<Located<U>, Located<E>> {
+        self.map(f).transpose()
+    }
+    /// chains a step producing its own located value,
+    /// the resulting position covers both positions
+    pub fn and_then<U, F: FnOnce(T) -> Located<U>>(self, f: F) -> Located<U> {
+        let Located { value, pos } = f(self.value);
+        Located {
+            value,
+            pos: self.pos.merge(&pos),
+        }
+    }
+    /// maps the position to a different position
+    pub fn map_pos<F: FnOnce(Position) -> Position>(self, f: F) -> Self {
+        Self {
+            value: self.value,
+            pos: f(self.pos),
+        }
+    }
+    /// pairs both values, the resulting position covers both positions
+    pub fn zip<U>(self, other: Located<U>) -> Located<(T, U)> {
+        Located {
+            pos: self.pos.merge(&other.pos),
+            value: (self.value, other.value),
+        }
+    }
+    /// the part of `content` the value was located at
+    pub fn slice<'a>(&self, content: &'a str) -> Option<&'a str> {
+        self.pos.slice(content)
+    }
     pub fn with_path(self, path: Box<Path>) -> PathLocated<T> {
         PathLocated {
             value: self.value,
@@ -68,6 +272,31 @@ impl<T> Located<T> {
             path,
         }
     }
+    pub fn with_file(self, file: FileId) -> FileLocated<T> {
+        FileLocated {
+            value: self.value,
+            pos: self.pos,
+            file,
+        }
+    }
+    /// borrows the inner value, keeping the position
+    pub fn as_ref(&self) -> Located<&T> {
+        Located {
+            value: &self.value,
+            pos: self.pos.clone(),
+        }
+    }
+    /// mutably borrows the inner value, keeping the position
+    pub fn as_mut(&mut self) -> Located<&mut T> {
+        Located {
+            value: &mut self.value,
+            pos: self.pos.clone(),
+        }
+    }
+    /// splits it into its fields
+    pub fn into_parts(self) -> (T, Position) {
+        (self.value, self.pos)
+    }
 }
 impl<T: Default> Located<T> {
     /// only position and `T::default()`
@@ -86,6 +315,62 @@ impl<T: Default> Default for Located<T> {
         }
     }
 }
+impl<T> Located<Option<T>> {
+    /// `None` if there is no inner value, otherwise the located inner value
+    pub fn transpose(self) -> Option<Located<T>> {
+        let pos = self.pos;
+        self.value.map(|value| Located { value, pos })
+    }
+}
+impl<T, E> Located<Result<T, E>> {
+    /// the located inner value or the located inner error
+    pub fn transpose(self) -> Result<Located<T>, Located<E>> {
+        let pos = self.pos;
+        match self.value {
+            Ok(value) => Ok(Located { value, pos }),
+            Err(value) => Err(Located { value, pos }),
+        }
+    }
+}
+impl<T: Clone> Located<&T> {
+    /// clones the borrowed inner value
+    pub fn cloned(self) -> Located<T> {
+        Located {
+            value: self.value.clone(),
+            pos: self.pos,
+        }
+    }
+}
+impl<T: Copy> Located<&T> {
+    /// copies the borrowed inner value
+    pub fn copied(self) -> Located<T> {
+        Located {
+            value: *self.value,
+            pos: self.pos,
+        }
+    }
+}
+impl<T> Deref for Located<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+impl<T> DerefMut for Located<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+impl<T> AsRef<T> for Located<T> {
+    fn as_ref(&self) -> &T {
+        &self.value
+    }
+}
+impl<T> AsMut<T> for Located<T> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
 impl<T: Debug> Debug for Located<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.value.fmt(f)
@@ -111,36 +396,121 @@ impl<T: PartialEq> PartialEq for Located<T> {
     }
 }
 impl<T: Eq> Eq for Located<T> {}
+impl<T: PartialEq> Located<T> {
+    /// compares the inner values and the positions
+    pub fn eq_with_pos(&self, other: &Self) -> bool {
+        self.value == other.value && self.pos == other.pos
+    }
+}
 impl<T: Hash> Hash for Located<T> {
+    /// only the inner value gets hashed, consistent with `PartialEq`
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.value.hash(state);
-        self.pos.hash(state);
     }
 }
 impl<T> PathLocated<T> {
     pub fn new(value: T, pos: Position, path: Box<Path>) -> Self {
         Self { value, pos, path }
     }
+}
+impl<T, P> PathLocated<T, P> {
     /// maps the inner value to a different value
-    pub fn map<U, F: Fn(T) -> U>(self, f: F) -> PathLocated<U> {
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> PathLocated<U, P> {
         PathLocated {
             value: f(self.value),
             pos: self.pos,
             path: self.path,
         }
     }
+    /// splits it into its fields
+    pub fn into_parts(self) -> (T, Position, P) {
+        (self.value, self.pos, self.path)
+    }
+}
+impl<T, P: AsRef<Path>> PathLocated<T, P> {
+    /// the span covering both positions, `None` if they are in different files
+    pub fn try_merge<U, Q: AsRef<Path>>(&self, other: &PathLocated<U, Q>) -> Option<Position> {
+        (self.path.as_ref() == other.path.as_ref()).then(|| self.pos.merge(&other.pos))
+    }
+    /// borrows the inner value and the path, keeping the position
+    pub fn as_ref(&self) -> PathLocated<&T, &Path> {
+        PathLocated {
+            value: &self.value,
+            pos: self.pos.clone(),
+            path: self.path.as_ref(),
+        }
+    }
+    /// mutably borrows the inner value and borrows the path, keeping the position
+    pub fn as_mut(&mut self) -> PathLocated<&mut T, &Path> {
+        PathLocated {
+            value: &mut self.value,
+            pos: self.pos.clone(),
+            path: self.path.as_ref(),
+        }
+    }
+}
+impl<T> PathLocated<T, &Path> {
+    /// copies the borrowed path into an owned one
+    pub fn into_owned(self) -> PathLocated<T> {
+        PathLocated {
+            value: self.value,
+            pos: self.pos,
+            path: self.path.into(),
+        }
+    }
+}
+impl<T: Clone, P> PathLocated<&T, P> {
+    /// clones the borrowed inner value
+    pub fn cloned(self) -> PathLocated<T, P> {
+        PathLocated {
+            value: self.value.clone(),
+            pos: self.pos,
+            path: self.path,
+        }
+    }
+}
+impl<T: Copy, P> PathLocated<&T, P> {
+    /// copies the borrowed inner value
+    pub fn copied(self) -> PathLocated<T, P> {
+        PathLocated {
+            value: *self.value,
+            pos: self.pos,
+            path: self.path,
+        }
+    }
+}
+impl<T, P> Deref for PathLocated<T, P> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+impl<T, P> DerefMut for PathLocated<T, P> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+impl<T, P> AsRef<T> for PathLocated<T, P> {
+    fn as_ref(&self) -> &T {
+        &self.value
+    }
+}
+impl<T, P> AsMut<T> for PathLocated<T, P> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
 }
-impl<T: Debug> Debug for PathLocated<T> {
+impl<T: Debug, P> Debug for PathLocated<T, P> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.value.fmt(f)
     }
 }
-impl<T: Display> Display for PathLocated<T> {
+impl<T: Display, P> Display for PathLocated<T, P> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.value.fmt(f)
     }
 }
-impl<T: Clone> Clone for PathLocated<T> {
+impl<T: Clone, P: Clone> Clone for PathLocated<T, P> {
     fn clone(&self) -> Self {
         Self {
             value: self.value.clone(),
@@ -149,77 +519,278 @@ impl<T: Clone> Clone for PathLocated<T> {
         }
     }
 }
-impl<T: PartialEq> PartialEq for PathLocated<T> {
+impl<T: PartialEq, P> PartialEq for PathLocated<T, P> {
+    /// only the inner values get compared
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+impl<T: Eq, P> Eq for PathLocated<T, P> {}
+impl<T: PartialEq, P: AsRef<Path>> PathLocated<T, P> {
+    /// compares the inner values, the positions and the paths
+    pub fn eq_with_pos(&self, other: &Self) -> bool {
+        self.value == other.value
+            && self.pos == other.pos
+            && self.path.as_ref() == other.path.as_ref()
+    }
+}
+impl<T: Hash, P> Hash for PathLocated<T, P> {
+    /// only the inner value gets hashed, consistent with `PartialEq`
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+impl<T> FileLocated<T> {
+    pub fn new(value: T, pos: Position, file: FileId) -> Self {
+        Self { value, pos, file }
+    }
+    /// maps the inner value to a different value
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> FileLocated<U> {
+        FileLocated {
+            value: f(self.value),
+            pos: self.pos,
+            file: self.file,
+        }
+    }
+    /// the span covering both positions, `None` if they are in different files
+    pub fn try_merge<U>(&self, other: &FileLocated<U>) -> Option<Position> {
+        (self.file == other.file).then(|| self.pos.merge(&other.pos))
+    }
+    /// resolves the file id to a `PathLocated<T>`
+    pub fn with_path(self, map: &SourceMap) -> Option<PathLocated<T>> {
+        let path = map.path(self.file)?.into();
+        Some(PathLocated {
+            value: self.value,
+            pos: self.pos,
+            path,
+        })
+    }
+    /// borrows the inner value, keeping the position
+    pub fn as_ref(&self) -> FileLocated<&T> {
+        FileLocated {
+            value: &self.value,
+            pos: self.pos.clone(),
+            file: self.file,
+        }
+    }
+    /// mutably borrows the inner value, keeping the position
+    pub fn as_mut(&mut self) -> FileLocated<&mut T> {
+        FileLocated {
+            value: &mut self.value,
+            pos: self.pos.clone(),
+            file: self.file,
+        }
+    }
+    /// splits it into its fields
+    pub fn into_parts(self) -> (T, Position, FileId) {
+        (self.value, self.pos, self.file)
+    }
+}
+impl<T: Clone> FileLocated<&T> {
+    /// clones the borrowed inner value
+    pub fn cloned(self) -> FileLocated<T> {
+        FileLocated {
+            value: self.value.clone(),
+            pos: self.pos,
+            file: self.file,
+        }
+    }
+}
+impl<T: Copy> FileLocated<&T> {
+    /// copies the borrowed inner value
+    pub fn copied(self) -> FileLocated<T> {
+        FileLocated {
+            value: *self.value,
+            pos: self.pos,
+            file: self.file,
+        }
+    }
+}
+impl<T> Deref for FileLocated<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+impl<T> DerefMut for FileLocated<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+impl<T> AsRef<T> for FileLocated<T> {
+    fn as_ref(&self) -> &T {
+        &self.value
+    }
+}
+impl<T> AsMut<T> for FileLocated<T> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+impl<T: Debug> Debug for FileLocated<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+impl<T: Display> Display for FileLocated<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+impl<T: Clone> Clone for FileLocated<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            pos: self.pos.clone(),
+            file: self.file,
+        }
+    }
+}
+impl<T: PartialEq> PartialEq for FileLocated<T> {
     /// only the inner values get compared
     fn eq(&self, other: &Self) -> bool {
         self.value == other.value
     }
 }
-impl<T: Eq> Eq for PathLocated<T> {}
-impl<T: Hash> Hash for PathLocated<T> {
+impl<T: Eq> Eq for FileLocated<T> {}
+impl<T: PartialEq> FileLocated<T> {
+    /// compares the inner values, the positions and the files
+    pub fn eq_with_pos(&self, other: &Self) -> bool {
+        self.value == other.value && self.pos == other.pos && self.file == other.file
+    }
+}
+impl<T: Hash> Hash for FileLocated<T> {
+    /// only the inner value gets hashed, consistent with `PartialEq`
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.value.hash(state);
-        self.pos.hash(state);
-        self.path.hash(state);
+    }
+}
+impl<T: PartialEq> PartialEq for Strict<Located<T>> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_with_pos(&other.0)
+    }
+}
+impl<T: Eq> Eq for Strict<Located<T>> {}
+impl<T: Hash> Hash for Strict<Located<T>> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.value.hash(state);
+        self.0.pos.hash(state);
+    }
+}
+impl<T: Debug> Debug for Strict<Located<T>> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Located")
+            .field("value", &self.0.value)
+            .field("pos", &self.0.pos)
+            .finish()
+    }
+}
+impl<T: PartialEq, P: AsRef<Path>> PartialEq for Strict<PathLocated<T, P>> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_with_pos(&other.0)
+    }
+}
+impl<T: Eq, P: AsRef<Path>> Eq for Strict<PathLocated<T, P>> {}
+impl<T: Hash, P: AsRef<Path>> Hash for Strict<PathLocated<T, P>> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.value.hash(state);
+        self.0.pos.hash(state);
+        self.0.path.as_ref().hash(state);
+    }
+}
+impl<T: Debug, P: AsRef<Path>> Debug for Strict<PathLocated<T, P>> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PathLocated")
+            .field("value", &self.0.value)
+            .field("pos", &self.0.pos)
+            .field("path", &self.0.path.as_ref())
+            .finish()
+    }
+}
+impl<T: PartialEq> PartialEq for Strict<FileLocated<T>> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_with_pos(&other.0)
+    }
+}
+impl<T: Eq> Eq for Strict<FileLocated<T>> {}
+impl<T: Hash> Hash for Strict<FileLocated<T>> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.value.hash(state);
+        self.0.pos.hash(state);
+        self.0.file.hash(state);
+    }
+}
+impl<T: Debug> Debug for Strict<FileLocated<T>> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileLocated")
+            .field("value", &self.0.value)
+            .field("pos", &self.0.pos)
+            .field("file", &self.0.file)
+            .finish()
     }
 }
 
 impl Position {
-    pub fn display(&self, f: &mut String, content: &str) -> std::fmt::Result {
-        let lines = content.lines().collect::<Vec<&str>>();
-        let Some(lines) = lines.get(self.ln.start..=self.ln.end) else {
-            writeln!(f, "... code snippet unavailable ...")?;
-            return Ok(());
-        };
-        if lines.is_empty() {
-            writeln!(f, "... code snippet unavailable ...")?;
-            return Ok(());
-        }
-        let tab = 4;
-        if lines.len() == 1 {
-            let line = lines[0];
-            let ln = self.ln.start;
-            writeln!(f, "{:>tab$}| {line}", ln + 1)?;
-            writeln!(
-                f,
-                "{:>tab$}  {}",
-                "",
-                line.char_indices()
-                    .map(|(col, _)| if self.col.start <= col && self.col.end > col {
-                        '~'
-                    } else {
-                        ' '
-                    })
-                    .collect::<String>(),
-            )?;
-        } else {
-            let last_ln = lines.len() - 1;
-            for (ln, line) in lines.iter().copied().enumerate() {
-                writeln!(f, "{:>tab$}| {line}", ln + 1)?;
-                if ln == 0 {
-                    writeln!(
-                        f,
-                        "{:>tab$}  {}",
-                        "",
-                        line.char_indices()
-                            .map(|(col, _)| if self.col.start <= col { '~' } else { ' ' })
-                            .collect::<String>(),
-                    )?;
-                } else if ln == last_ln {
-                    writeln!(
-                        f,
-                        "{:>tab$}  {}",
-                        "",
-                        line.char_indices()
-                            .map(|(col, _)| if self.col.end > col { '~' } else { ' ' })
-                            .collect::<String>(),
-                    )?;
-                } else {
-                    writeln!(f, "{:>tab$}  {}", "", "~".repeat(line.len()),)?;
-                }
-            }
+    /// renders the snippet of `content` with lines split at
+    /// `LineEndings::default()`, `display_in` respects the ones of a `SourceFile`
+    pub fn display(&self, f: &mut impl Write, content: &str) -> std::fmt::Result {
+        self.display_in(f, &SourceFile::new(content))
+    }
+    /// like `display` but uses the precomputed line index of `source`
+    pub fn display_in(&self, f: &mut impl Write, source: &SourceFile) -> std::fmt::Result {
+        self.display_with(f, source, &RenderConfig::default())
+    }
+    /// like `display_in` but rendered according to `config`,
+    /// `ColorChoice::Auto` colors if stderr is a terminal
+    pub fn display_with(
+        &self,
+        f: &mut impl Write,
+        source: &SourceFile,
+        config: &RenderConfig,
+    ) -> std::fmt::Result {
+        self.display_resolved(f, source, &config.resolved(io::stderr().is_terminal()))
+    }
+    fn display_resolved(
+        &self,
+        f: &mut impl Write,
+        source: &SourceFile,
+        config: &RenderConfig,
+    ) -> std::fmt::Result {
+        let gutter = config.gutter(config.last_shown(source, self.ln.end));
+        if config.separator {
+            writeln!(f, "{}", config.bar(gutter))?;
         }
-        Ok(())
+        let mark = render::Mark {
+            pos: self,
+            marker: config.primary_marker,
+            style: render::Style::Severity(Severity::Error),
+            label: "",
+        };
+        render::snippet(f, source, config, gutter, &[mark])
+    }
+    /// like `display` but writes to an `io::Write` like stderr
+    pub fn write(&self, w: &mut impl io::Write, content: &str) -> io::Result<()> {
+        render::write_io(w, |f| self.display(f, content))
+    }
+    /// like `display_with` but writes to an `io::Write` like stderr,
+    /// `ColorChoice::Auto` colors if `w` is a terminal
+    pub fn write_with<W: io::Write + IsTerminal>(
+        &self,
+        w: &mut W,
+        source: &SourceFile,
+        config: &RenderConfig,
+    ) -> io::Result<()> {
+        let config = config.resolved(w.is_terminal());
+        render::write_io(w, |f| self.display_resolved(f, source, &config))
+    }
+    /// adapter implementing `Display` for the snippet of `content`,
+    /// for using it in `format!` or `eprintln!`
+    pub fn snippet<'a>(&'a self, content: &str) -> Snippet<'a> {
+        Snippet::new(self, Cow::Owned(SourceFile::new(content)))
+    }
+    /// like `snippet` but uses the precomputed line index of `source`
+    pub fn snippet_in<'a>(&'a self, source: &'a SourceFile) -> Snippet<'a> {
+        Snippet::new(self, Cow::Borrowed(source))
     }
 }
 
@@ -227,13 +798,139 @@ impl Position {
 mod tests {
     use super::*;
     #[test]
+    fn offsets() {
+        let text = "hello man\n  i like pizza";
+        let pos = Position::from_offset(12..18, text);
+        assert_eq!(pos.ln, 1..1);
+        assert_eq!(pos.col, 2..8);
+        assert_eq!(pos.slice(text), Some("i like"));
+        let pos = Position::from_offset(6..13, text);
+        assert_eq!(pos.ln, 0..1);
+        assert_eq!((pos.col.start, pos.col.end), (6, 3));
+        let without = Position::new(0..1, Range { start: 6, end: 3 });
+        assert_eq!(without, pos);
+        let set = std::collections::HashSet::from([pos.clone(), without]);
+        assert_eq!(set.len(), 1);
+        assert_eq!(Position::new(0..1, pos.col).slice(text), Some("man\n  i"));
+        assert_eq!(Position::new(0..0, 0..5).slice("ab\ncd"), None);
+        assert_eq!(Position::new(0..0, 0..3).slice("ab\ncd"), Some("ab\n"));
+    }
+    #[test]
+    fn extend() {
+        let mut pos = Position::new(2..2, 10..12);
+        pos.extend(&Position::new(4..4, 2..3));
+        assert_eq!(pos, Position::new(2..4, Range { start: 10, end: 3 }));
+        assert_eq!(
+            Position::new(4..4, 2..3).merge(&Position::new(2..2, 10..12)),
+            pos
+        );
+        let a = PathLocated::new((), pos.clone(), Path::new("a").into());
+        let b = PathLocated::new((), pos.clone(), Path::new("b").into());
+        assert_eq!(a.try_merge(&a), Some(pos));
+        assert_eq!(a.try_merge(&b), None);
+    }
+    #[test]
+    fn writers() {
+        let text = "hello man\n  i like pizza";
+        let pos = Position::new(1..1, 4..8);
+        let mut display = String::new();
+        pos.display(&mut display, text).unwrap();
+        let mut bytes = vec![];
+        pos.write(&mut bytes, text).unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), display);
+        assert_eq!(format!("{}", pos.snippet(text)), display);
+        let path = std::env::temp_dir().join("parse-pos-writers.txt");
+        let mut file = std::fs::File::create(&path).unwrap();
+        let config = RenderConfig::default().with_color(ColorChoice::Auto);
+        pos.write_with(&mut file, &SourceFile::new(text), &config)
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), display);
+    }
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let located = Located::new("x", Position::new(1..1, 2..3).with_offset(12..13))
+            .with_path(Path::new("main.txt").into());
+        let json = serde_json::to_string(&located).unwrap();
+        assert_eq!(
+            json,
+            r#"{"value":"x","pos":{"ln":{"start":1,"end":1},"col":{"start":2,"end":3},"offset":{"start":12,"end":13}},"path":"main.txt"}"#
+        );
+        let back: PathLocated<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.pos, located.pos);
+        assert_eq!(back.path, located.path);
+    }
+    #[test]
+    fn smart_pointer() {
+        let mut located = Located::new(vec![1, 2], Position::new(0..0, 1..3));
+        located.push(3);
+        assert_eq!(located.len(), 3);
+        let first = located.as_ref().map(|values| values[0]);
+        assert_eq!((first.value, &first.pos), (1, &located.pos));
+        located.as_mut().value.clear();
+        assert!(located.is_empty());
+        let path = Located::new(2, Position::default()).with_path(Path::new("a").into());
+        let view = path.as_ref();
+        assert!(std::ptr::eq(view.path, &*path.path));
+        let copied = view.copied().into_owned();
+        let (value, pos, path) = copied.into_parts();
+        assert_eq!(
+            (value, pos, &*path),
+            (2, Position::default(), Path::new("a"))
+        );
+        let borrowed: &Vec<i32> = AsRef::as_ref(&located);
+        assert!(borrowed.is_empty());
+    }
+    #[test]
+    fn combinators() {
+        let number = Located::new("42", Position::new(0..0, 0..2));
+        let parsed = number.try_map(str::parse::<u8>).unwrap();
+        assert_eq!(parsed.value, 42);
+        let error = Located::new("x", Position::new(0..0, 3..4)).try_map(str::parse::<u8>);
+        assert_eq!(error.unwrap_err().pos, Position::new(0..0, 3..4));
+        let pair = parsed.zip(Located::new('+', Position::new(1..1, 0..1)));
+        assert_eq!(pair.pos, Position::new(0..1, 0..1));
+        assert_eq!(
+            Located::new(None::<u8>, Position::default()).transpose(),
+            None
+        );
+        let some = Located::new(Some(1), Position::new(0..0, 1..2))
+            .transpose()
+            .unwrap();
+        assert_eq!(some.pos, Position::new(0..0, 1..2));
+        let chained = some.and_then(|value| Located::new(value + 1, Position::new(0..0, 4..5)));
+        assert_eq!((chained.value, chained.pos), (2, Position::new(0..0, 1..5)));
+        let shifted = Located::new((), Position::new(0..0, 1..2))
+            .map_pos(|pos| pos.merge(&Position::point(0, 0)));
+        assert_eq!(shifted.pos, Position::new(0..0, 0..2));
+    }
+    #[test]
+    fn equality() {
+        use std::collections::{HashMap, HashSet};
+        let a = Located::new("x", Position::new(0..0, 0..1));
+        let b = Located::new("x", Position::new(3..3, 4..5));
+        assert_eq!(a, b);
+        let mut map = HashMap::new();
+        map.insert(a.clone(), 1);
+        assert_eq!(map.get(&b), Some(&1));
+        assert!(!a.eq_with_pos(&b));
+        assert_ne!(Strict(a.as_ref()), Strict(b.as_ref()));
+        let set = [a.clone(), b, a]
+            .map(Strict)
+            .into_iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(set.len(), 2);
+    }
+    #[test]
     fn test() {
         let text = "hello man\n  i like pizza";
         let mut display = String::new();
         Position::new(0..1, 1..5)
             .display(&mut display, text)
             .unwrap();
-        println!("{display}");
-        panic!();
+        assert_eq!(
+            display,
+            "  |\n1 |   hello man\n  |  __^\n2 | |   i like pizza\n  | |_____^\n"
+        );
     }
 }
@@ -0,0 +1,152 @@
+use crate::{ColumnUnit, Position, SourceFile};
+
+/// the `positionEncoding` negotiated with an LSP client, what `character` counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PositionEncoding {
+    Utf8,
+    /// the encoding every client supports
+    #[default]
+    Utf16,
+    Utf32,
+}
+/// LSP `Position`, a zero based line and character offset into it
+///
+/// with the `serde` feature it's (de)serialized as in the protocol, `{"line": 0, "character": 0}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LspPosition {
+    pub line: u32,
+    pub character: u32,
+}
+/// LSP `Range`, with an exclusive end
+///
+/// with the `serde` feature it's (de)serialized as in the protocol, `{"start": .., "end": ..}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LspRange {
+    pub start: LspPosition,
+    pub end: LspPosition,
+}
+
+impl PositionEncoding {
+    /// parses the `PositionEncodingKind` strings `"utf-8"`, `"utf-16"` and `"utf-32"`
+    pub fn from_kind(kind: &str) -> Option<Self> {
+        match kind {
+            "utf-8" => Some(Self::Utf8),
+            "utf-16" => Some(Self::Utf16),
+            "utf-32" => Some(Self::Utf32),
+            _ => None,
+        }
+    }
+    /// the `PositionEncodingKind` string
+    pub fn kind(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16 => "utf-16",
+            Self::Utf32 => "utf-32",
+        }
+    }
+    /// the first of the client's supported encodings this crate knows,
+    /// UTF-16 if there is none as the protocol requires
+    pub fn negotiate<'a>(kinds: impl IntoIterator<Item = &'a str>) -> Self {
+        kinds
+            .into_iter()
+            .find_map(Self::from_kind)
+            .unwrap_or_default()
+    }
+    pub fn unit(self) -> ColumnUnit {
+        match self {
+            Self::Utf8 => ColumnUnit::Bytes,
+            Self::Utf16 => ColumnUnit::Utf16,
+            Self::Utf32 => ColumnUnit::Chars,
+        }
+    }
+}
+impl LspPosition {
+    pub fn new(line: u32, character: u32) -> Self {
+        Self { line, character }
+    }
+}
+impl LspRange {
+    pub fn new(start: LspPosition, end: LspPosition) -> Self {
+        Self { start, end }
+    }
+}
+impl SourceFile {
+    /// converts the byte column `col` on line `ln` to an LSP position
+    pub fn to_lsp_position(
+        &self,
+        ln: usize,
+        col: usize,
+        encoding: PositionEncoding,
+    ) -> Option<LspPosition> {
+        let character = self.column(ln, col, encoding.unit())?;
+        Some(LspPosition::new(
+            ln.try_into().ok()?,
+            character.try_into().ok()?,
+        ))
+    }
+    /// converts an LSP position to a line and byte column, a character past
+    /// the end of the line is clamped to it as the protocol asks
+    pub fn from_lsp_position(
+        &self,
+        position: LspPosition,
+        encoding: PositionEncoding,
+    ) -> Option<(usize, usize)> {
+        let ln = position.line as usize;
+        let line = self.line(ln)?;
+        let character = (position.character as usize).min(encoding.unit().measure(line));
+        Some((ln, self.byte_column(ln, character, encoding.unit())?))
+    }
+    /// converts `pos` to an LSP range
+    pub fn to_lsp_range(&self, pos: &Position, encoding: PositionEncoding) -> Option<LspRange> {
+        Some(LspRange::new(
+            self.to_lsp_position(pos.ln.start, pos.col.start, encoding)?,
+            self.to_lsp_position(pos.ln.end, pos.col.end, encoding)?,
+        ))
+    }
+    /// converts an LSP range to a `Position` with byte offsets
+    pub fn from_lsp_range(&self, range: LspRange, encoding: PositionEncoding) -> Option<Position> {
+        let (ln_start, col_start) = self.from_lsp_position(range.start, encoding)?;
+        let (ln_end, col_end) = self.from_lsp_position(range.end, encoding)?;
+        let start = self.offset(ln_start, col_start)?;
+        let end = self.offset(ln_end, col_end)?;
+        (start <= end).then(|| self.position(start..end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn encodings() {
+        let source = SourceFile::new("x\nlet \u{1f600} = \"\u{e9}\";\n");
+        let text = source.text();
+        let start = text.find('"').unwrap();
+        let pos = source.position(start..text.rfind('"').unwrap() + 1);
+        let range =
+            |start, end| LspRange::new(LspPosition::new(1, start), LspPosition::new(1, end));
+        for (encoding, lsp) in [
+            (PositionEncoding::Utf8, range(11, 15)),
+            (PositionEncoding::Utf16, range(9, 12)),
+            (PositionEncoding::Utf32, range(8, 11)),
+        ] {
+            assert_eq!(source.to_lsp_range(&pos, encoding), Some(lsp));
+            assert_eq!(source.from_lsp_range(lsp, encoding), Some(pos.clone()));
+        }
+        let inside = LspPosition::new(1, 5);
+        assert_eq!(
+            source.from_lsp_position(inside, PositionEncoding::Utf16),
+            None
+        );
+        let past = LspPosition::new(0, 10);
+        assert_eq!(
+            source.from_lsp_position(past, PositionEncoding::Utf16),
+            Some((0, 1))
+        );
+        assert_eq!(
+            PositionEncoding::negotiate(["utf-32", "utf-8"]),
+            PositionEncoding::Utf32
+        );
+    }
+}
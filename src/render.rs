@@ -0,0 +1,753 @@
+use std::{
+    borrow::Cow,
+    fmt::{Display, Write},
+    io::{self, IsTerminal},
+    ops::Range,
+};
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::{Position, Severity, SourceFile};
+
+/// options for rendering snippets
+///
+/// the defaults follow rustc's layout (`N |` gutters, `^` markers and a
+/// separator line), which replaced the old `~` underlines, `classic` gets
+/// closest to those
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RenderConfig {
+    /// tabs are expanded to the next multiple of this many columns
+    pub tab_width: usize,
+    /// whether to color the output with ANSI escapes
+    pub color: ColorChoice,
+    /// minimum width of the line number gutter, `None` sizes it to the largest line number
+    pub gutter_width: Option<usize>,
+    /// number of lines shown before and after each span
+    pub context_lines: usize,
+    /// underlines the main spans
+    pub primary_marker: char,
+    /// underlines spans giving additional context
+    pub secondary_marker: char,
+    /// whether an empty `|` line separates the snippet from what's above
+    pub separator: bool,
+    /// spans covering more lines than this only show their first and last
+    /// `span_edge_lines` lines, `None` always shows every line
+    pub max_span_lines: Option<usize>,
+    /// number of lines shown at the start and end of an elided span,
+    /// at least 1
+    pub span_edge_lines: usize,
+    /// lines displayed wider than this are cut down to the part around the
+    /// spans on them, `None` always shows the whole line
+    pub max_width: Option<usize>,
+}
+/// when to color rendered output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColorChoice {
+    Always,
+    /// color unless `NO_COLOR` is set or the output isn't a terminal,
+    /// which is stderr for anything not written to an `io::Write`
+    Auto,
+    #[default]
+    Never,
+}
+/// what a piece of colored output is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Style {
+    Severity(Severity),
+    Secondary,
+    Gutter,
+    Emphasis,
+}
+/// `Display` adapter for the snippet of a `Position`, made by `Position::snippet`
+pub struct Snippet<'a> {
+    pos: &'a Position,
+    source: Cow<'a, SourceFile>,
+    config: RenderConfig,
+}
+/// `text` wrapped in the ANSI escapes of `style` if there is one
+pub(crate) struct Painted<T> {
+    style: Option<Style>,
+    text: T,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            tab_width: 4,
+            color: ColorChoice::default(),
+            gutter_width: None,
+            context_lines: 0,
+            primary_marker: '^',
+            secondary_marker: '-',
+            separator: true,
+            max_span_lines: Some(8),
+            span_edge_lines: 2,
+            max_width: Some(120),
+        }
+    }
+}
+impl RenderConfig {
+    /// `~` markers without a separator line, close to how snippets looked
+    /// before the rustc layout became the default
+    pub fn classic() -> Self {
+        Self {
+            gutter_width: Some(3),
+            primary_marker: '~',
+            secondary_marker: '~',
+            separator: false,
+            ..Self::default()
+        }
+    }
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width;
+        self
+    }
+    pub fn with_color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+    pub fn with_gutter_width(mut self, gutter_width: usize) -> Self {
+        self.gutter_width = Some(gutter_width);
+        self
+    }
+    pub fn with_context_lines(mut self, context_lines: usize) -> Self {
+        self.context_lines = context_lines;
+        self
+    }
+    pub fn with_markers(mut self, primary: char, secondary: char) -> Self {
+        self.primary_marker = primary;
+        self.secondary_marker = secondary;
+        self
+    }
+    pub fn with_separator(mut self, separator: bool) -> Self {
+        self.separator = separator;
+        self
+    }
+    pub fn with_max_span_lines(mut self, max_span_lines: Option<usize>) -> Self {
+        self.max_span_lines = max_span_lines;
+        self
+    }
+    pub fn with_span_edge_lines(mut self, span_edge_lines: usize) -> Self {
+        self.span_edge_lines = span_edge_lines;
+        self
+    }
+    pub fn with_max_width(mut self, max_width: Option<usize>) -> Self {
+        self.max_width = max_width;
+        self
+    }
+    /// width of the gutter when `max_ln` is the largest line shown
+    pub(crate) fn gutter(&self, max_ln: usize) -> usize {
+        let digits = (max_ln + 1).to_string().len();
+        self.gutter_width.map_or(digits, |width| width.max(digits))
+    }
+    /// the last line shown for a span ending on line `ln`
+    pub(crate) fn last_shown(&self, source: &SourceFile, ln: usize) -> usize {
+        (ln + self.context_lines)
+            .min(source.line_count() - 1)
+            .max(ln)
+    }
+    /// the empty gutter in front of underlines and notes
+    pub(crate) fn bar(&self, gutter: usize) -> Painted<String> {
+        self.paint(Style::Gutter, format!("{:gutter$} |", ""))
+    }
+    /// the config with `ColorChoice::Auto` decided once for a whole render,
+    /// `terminal` being whether the output goes to a terminal
+    pub(crate) fn resolved(&self, terminal: bool) -> Self {
+        Self {
+            color: self.color.resolve_for(terminal),
+            ..self.clone()
+        }
+    }
+    /// `text` painted in `style` if colors are enabled,
+    /// `Auto` has to be decided with `resolved` first
+    pub(crate) fn paint<T: Display>(&self, style: Style, text: T) -> Painted<T> {
+        Painted {
+            style: (self.color == ColorChoice::Always).then_some(style),
+            text,
+        }
+    }
+}
+impl<'a> Snippet<'a> {
+    pub(crate) fn new(pos: &'a Position, source: Cow<'a, SourceFile>) -> Self {
+        Self {
+            pos,
+            source,
+            config: RenderConfig::default(),
+        }
+    }
+    pub fn with_config(mut self, config: RenderConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+impl Display for Snippet<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.pos.display_with(f, &self.source, &self.config)
+    }
+}
+impl ColorChoice {
+    /// resolves `Auto` against the `NO_COLOR` environment variable and stderr
+    pub fn enabled(self) -> bool {
+        self.resolve(std::io::stderr().is_terminal())
+    }
+    /// resolves `Auto` for output going to `stream` instead of stderr,
+    /// so pipes and files don't get colored
+    pub fn for_stream(self, stream: &impl IsTerminal) -> Self {
+        self.resolve_for(stream.is_terminal())
+    }
+    fn resolve_for(self, terminal: bool) -> Self {
+        match self.resolve(terminal) {
+            true => Self::Always,
+            false => Self::Never,
+        }
+    }
+    fn resolve(self, terminal: bool) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && terminal
+            }
+        }
+    }
+}
+impl Style {
+    fn escape(self) -> &'static str {
+        match self {
+            Self::Severity(Severity::Error) => "\x1b[1;31m",
+            Self::Severity(Severity::Warning) => "\x1b[1;33m",
+            Self::Severity(Severity::Note) => "\x1b[1;32m",
+            Self::Severity(Severity::Help) => "\x1b[1;36m",
+            Self::Secondary | Self::Gutter => "\x1b[1;34m",
+            Self::Emphasis => "\x1b[1m",
+        }
+    }
+}
+impl<T: Display> Display for Painted<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.style {
+            Some(style) => write!(f, "{}{}\x1b[0m", style.escape(), self.text),
+            None => self.text.fmt(f),
+        }
+    }
+}
+
+/// a `Position` to underline in a snippet
+pub(crate) struct Mark<'a> {
+    pub pos: &'a Position,
+    pub marker: char,
+    pub style: Style,
+    pub label: &'a str,
+}
+
+/// where a mark is drawn, a span ending right after a line break is drawn
+/// up to the end of the line before
+struct Region<'a> {
+    mark: &'a Mark<'a>,
+    start: (usize, usize),
+    end: (usize, usize),
+    /// only whitespace precedes the start, so a connector starts on the line itself
+    indented: bool,
+}
+
+impl Region<'_> {
+    fn is_multiline(&self) -> bool {
+        self.start.0 < self.end.0
+    }
+    /// whether the connector of a multi-line region passes through line `ln`
+    fn continues(&self, ln: usize) -> bool {
+        (self.start.0 < ln && ln <= self.end.0) || (ln == self.start.0 && self.indented)
+    }
+}
+
+/// writes the lines covered by `marks` with their context lines, each line
+/// followed by the underlines of the marks on it
+pub(crate) fn snippet(
+    f: &mut impl Write,
+    source: &SourceFile,
+    config: &RenderConfig,
+    gutter: usize,
+    marks: &[Mark],
+) -> std::fmt::Result {
+    let mut regions = vec![];
+    for mark in marks {
+        let (ln, col) = (&mark.pos.ln, &mark.pos.col);
+        if ln.start > ln.end || ln.end >= source.line_count() {
+            writeln!(f, "... code snippet unavailable ...")?;
+            continue;
+        }
+        let mut end = (ln.end, col.end);
+        if ln.start < ln.end && col.end == 0 {
+            end = (
+                ln.end - 1,
+                source.line(ln.end - 1).unwrap_or_default().len(),
+            );
+        }
+        let before = source.line(ln.start).and_then(|line| line.get(..col.start));
+        regions.push(Region {
+            mark,
+            start: (ln.start, col.start),
+            end,
+            indented: before.is_some_and(|before| before.trim().is_empty()),
+        });
+    }
+    let mut windows: Vec<Range<usize>> = vec![];
+    for region in &regions {
+        let start = region.start.0.saturating_sub(config.context_lines);
+        let end = config.last_shown(source, region.end.0) + 1;
+        let lines = region.end.0 - region.start.0 + 1;
+        let edge = config.span_edge_lines.max(1);
+        match config.max_span_lines {
+            Some(max) if lines > max && lines > 2 * edge => {
+                windows.push(start..region.start.0 + edge);
+                windows.push(region.end.0 + 1 - edge..end);
+            }
+            _ => windows.push(start..end),
+        }
+    }
+    windows.sort_by_key(|window| window.start);
+    let mut merged: Vec<Range<usize>> = vec![];
+    for window in windows {
+        match merged.last_mut() {
+            Some(last) if window.start <= last.end => last.end = last.end.max(window.end),
+            _ => merged.push(window),
+        }
+    }
+    // outer connectors get the outer margin columns
+    let mut multilines = regions
+        .iter()
+        .filter(|region| region.is_multiline())
+        .collect::<Vec<&Region>>();
+    multilines.sort_by_key(|region| (region.start, std::cmp::Reverse(region.end)));
+    // the margin of an underline row, `own` is the connector drawing the row
+    let margin = |ln: usize, own: Option<(usize, char)>| {
+        let mut margin = String::new();
+        for (idx, multiline) in multilines.iter().enumerate() {
+            let c = match own {
+                Some((own, c)) if own == idx => c,
+                Some((own, _)) if own < idx => '_',
+                _ if multiline.continues(ln) => '|',
+                _ => ' ',
+            };
+            let fill = match own {
+                Some((own, _)) if own <= idx => '_',
+                _ => ' ',
+            };
+            let style = multiline.mark.style;
+            write!(margin, "{}", config.paint(style, format!("{c}{fill}"))).unwrap();
+        }
+        margin
+    };
+    for (idx, window) in merged.into_iter().enumerate() {
+        if idx > 0 {
+            let dots = config.paint(Style::Gutter, format!("{:<w$}", "...", w = gutter + 2));
+            let row = format!("{dots} {}", margin(window.start, None));
+            writeln!(f, "{}", row.trim_end())?;
+        }
+        for ln in window {
+            let line = source.line(ln).unwrap_or_default();
+            let clip = clip(line, ln, &regions, config);
+            let number = config.paint(Style::Gutter, format!("{:>gutter$} |", ln + 1));
+            let mut connectors = String::new();
+            for multiline in &multilines {
+                let c = match () {
+                    _ if ln == multiline.start.0 && multiline.indented => '/',
+                    _ if multiline.continues(ln) => '|',
+                    _ => ' ',
+                };
+                write!(connectors, "{} ", config.paint(multiline.mark.style, c))?;
+            }
+            if line.is_empty() && multilines.iter().all(|multiline| !multiline.continues(ln)) {
+                writeln!(f, "{number}")?;
+            } else if line.is_empty() {
+                writeln!(f, "{number} {}", connectors.trim_end())?;
+            } else {
+                let line = match &clip {
+                    Some(clip) => clip_line(line, config.tab_width, clip),
+                    None => expand_tabs(line, config.tab_width),
+                };
+                writeln!(f, "{number} {connectors}{line}")?;
+            }
+            for region in regions.iter().filter(|region| !region.is_multiline()) {
+                let mark = region.mark;
+                if region.start.0 != ln {
+                    continue;
+                }
+                let cols = region.start.1..region.end.1;
+                let mut markers = underline(line, cols, config.tab_width, mark.marker);
+                markers.truncate(markers.trim_end().len());
+                if markers.is_empty() {
+                    // zero width or past the end of the line, points between characters
+                    let col = display_col(line, region.start.1.min(line.len()), config.tab_width);
+                    markers = format!("{}{}", " ".repeat(col), mark.marker);
+                }
+                if let Some(clip) = &clip {
+                    markers = clip_markers(&markers, clip);
+                    markers.truncate(markers.trim_end().len());
+                }
+                if !mark.label.is_empty() {
+                    write!(markers, " {}", mark.label)?;
+                }
+                let margin = margin(ln, None);
+                writeln!(
+                    f,
+                    "{} {margin}{}",
+                    config.bar(gutter),
+                    config.paint(mark.style, markers)
+                )?;
+            }
+            for (idx, multiline) in multilines.iter().enumerate() {
+                let mark = multiline.mark;
+                if ln == multiline.start.0 && !multiline.indented {
+                    let col = display_col(line, multiline.start.1, config.tab_width);
+                    let mut markers = format!("{}{}", "_".repeat(col), mark.marker);
+                    if let Some(clip) = &clip {
+                        markers = clip_markers(&markers, clip);
+                    }
+                    writeln!(
+                        f,
+                        "{} {}{}",
+                        config.bar(gutter),
+                        margin(ln, Some((idx, ' '))),
+                        config.paint(mark.style, markers)
+                    )?;
+                } else if ln == multiline.end.0 {
+                    // the end column may fall inside a character, so
+                    // back off to the closest boundary before it
+                    let before = (0..=multiline.end.1.min(line.len()))
+                        .rev()
+                        .find_map(|end| line.get(..end))
+                        .unwrap_or_default();
+                    let last = before.char_indices().next_back().map_or(0, |(col, _)| col);
+                    let col = display_col(line, last, config.tab_width);
+                    let mut markers = format!("{}{}", "_".repeat(col), mark.marker);
+                    if let Some(clip) = &clip {
+                        markers = clip_markers(&markers, clip);
+                    }
+                    if !mark.label.is_empty() {
+                        write!(markers, " {}", mark.label)?;
+                    }
+                    writeln!(
+                        f,
+                        "{} {}{}",
+                        config.bar(gutter),
+                        margin(ln, Some((idx, '|'))),
+                        config.paint(mark.style, markers)
+                    )?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// runs `render` on a `fmt::Write` forwarding to `w`, keeping the io error
+pub(crate) fn write_io<W: io::Write>(
+    w: &mut W,
+    render: impl FnOnce(&mut IoWriter<'_, W>) -> std::fmt::Result,
+) -> io::Result<()> {
+    let mut writer = IoWriter {
+        inner: w,
+        error: None,
+    };
+    match render(&mut writer) {
+        Ok(()) => Ok(()),
+        Err(std::fmt::Error) => Err(writer
+            .error
+            .unwrap_or_else(|| io::Error::other("formatter error"))),
+    }
+}
+/// `fmt::Write` forwarding to an `io::Write`
+pub(crate) struct IoWriter<'a, W> {
+    inner: &'a mut W,
+    error: Option<io::Error>,
+}
+impl<W: io::Write> Write for IoWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            std::fmt::Error
+        })
+    }
+}
+
+/// the characters of `line` with their byte column and displayed width,
+/// tabs are as wide as needed to reach the next tab stop
+fn widths(line: &str, tab_width: usize) -> impl Iterator<Item = (usize, char, usize)> + '_ {
+    let mut column = 0;
+    line.char_indices().map(move |(col, c)| {
+        let width = match c {
+            '\t' if tab_width == 0 => 0,
+            '\t' => tab_width - column % tab_width,
+            c => c.width().unwrap_or(0),
+        };
+        column += width;
+        (col, c, width)
+    })
+}
+/// the display columns of line `ln` which fit into `config.max_width`,
+/// centered around the regions on it, `None` if the whole line fits
+fn clip(line: &str, ln: usize, regions: &[Region], config: &RenderConfig) -> Option<Range<usize>> {
+    let max_width = config.max_width?;
+    let width = display_col(line, line.len(), config.tab_width);
+    if width <= max_width {
+        return None;
+    }
+    let mut focus: Option<Range<usize>> = None;
+    for region in regions {
+        let cols = if region.start.0 == ln && region.end.0 == ln {
+            region.start.1..region.end.1
+        } else if region.start.0 == ln {
+            region.start.1..region.start.1 + 1
+        } else if region.end.0 == ln {
+            region.end.1.saturating_sub(1)..region.end.1
+        } else {
+            continue;
+        };
+        let start = display_col(line, cols.start, config.tab_width);
+        let end = display_col(line, cols.end, config.tab_width).max(start + 1);
+        focus = Some(match focus {
+            Some(focus) => focus.start.min(start)..focus.end.max(end),
+            None => start..end,
+        });
+    }
+    let focus = focus.unwrap_or(0..0);
+    let start = if focus.len() >= max_width {
+        focus.start
+    } else {
+        focus
+            .start
+            .saturating_sub((max_width - focus.len()) / 2)
+            .min(width - max_width)
+    };
+    Some(start..(start + max_width).min(width))
+}
+/// the display columns `clip` of `line`, with `...` where parts are cut off
+fn clip_line(line: &str, tab_width: usize, clip: &Range<usize>) -> String {
+    let mut clipped = String::new();
+    if clip.start > 0 {
+        clipped.push_str("...");
+    }
+    let mut column = 0;
+    for (_, c, width) in widths(line, tab_width) {
+        let visible = column >= clip.start && column + width <= clip.end;
+        let partly = column < clip.end && column + width > clip.start;
+        match c {
+            _ if !visible && partly => {
+                let shown = (column + width).min(clip.end) - column.max(clip.start);
+                clipped.extend(std::iter::repeat_n(' ', shown));
+            }
+            '\t' if visible => clipped.extend(std::iter::repeat_n(' ', width)),
+            c if visible => clipped.push(c),
+            _ => {}
+        }
+        column += width;
+    }
+    if clip.end < column {
+        clipped.push_str("...");
+    }
+    clipped
+}
+/// the display columns `clip` of an underline row, made up of one column wide characters
+fn clip_markers(markers: &str, clip: &Range<usize>) -> String {
+    let mut clipped = String::new();
+    if clip.start > 0 {
+        let cut = markers.chars().nth(clip.start - 1).unwrap_or(' ');
+        let fill = if cut == '_' { '_' } else { ' ' };
+        clipped.extend(std::iter::repeat_n(fill, 3));
+    }
+    clipped.extend(markers.chars().skip(clip.start).take(clip.len()));
+    clipped
+}
+/// display column at which the byte column `col` of `line` is shown
+fn display_col(line: &str, col: usize, tab_width: usize) -> usize {
+    widths(line, tab_width)
+        .take_while(|(idx, _, _)| *idx < col)
+        .map(|(_, _, width)| width)
+        .sum()
+}
+/// `line` with tabs replaced by spaces up to the next tab stop
+fn expand_tabs(line: &str, tab_width: usize) -> String {
+    if !line.contains('\t') {
+        return line.to_string();
+    }
+    let mut expanded = String::new();
+    for (_, c, width) in widths(line, tab_width) {
+        match c {
+            '\t' => expanded.extend(std::iter::repeat_n(' ', width)),
+            c => expanded.push(c),
+        }
+    }
+    expanded
+}
+/// `marker` under every character touching the byte columns `cols` and spaces
+/// under the rest, as wide as the characters are displayed in a terminal
+fn underline(line: &str, cols: Range<usize>, tab_width: usize, marker: char) -> String {
+    let mut markers = String::new();
+    for (col, c, width) in widths(line, tab_width) {
+        let marked = col < cols.end && col + c.len_utf8() > cols.start;
+        let fill = if marked { marker } else { ' ' };
+        markers.extend(std::iter::repeat_n(fill, width));
+    }
+    markers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mark<'a>(pos: &'a Position, label: &'a str) -> Mark<'a> {
+        Mark {
+            pos,
+            marker: '^',
+            style: Style::Emphasis,
+            label,
+        }
+    }
+    fn render(source: &SourceFile, config: &RenderConfig, gutter: usize, marks: &[Mark]) -> String {
+        let mut display = String::new();
+        snippet(&mut display, source, config, gutter, marks).unwrap();
+        display
+    }
+
+    #[test]
+    fn wide_characters() {
+        let line = "s = \"\u{4f60}\u{597d}\" + e\u{301}x";
+        let start = line.find('"').unwrap();
+        let end = line.rfind('"').unwrap() + 1;
+        assert_eq!(underline(line, start..end, 4, '~'), "    ~~~~~~     ");
+        let x = line.len() - 1;
+        assert_eq!(
+            underline(line, x - 3..x, 4, '~').trim_end(),
+            "             ~"
+        );
+    }
+    #[test]
+    fn tabs() {
+        let line = "\tif\tx";
+        assert_eq!(expand_tabs(line, 4), "    if  x");
+        assert_eq!(underline(line, 3..4, 4, '~'), "      ~~ ");
+        assert_eq!(expand_tabs(line, 2), "  if  x");
+        assert_eq!(underline(line, 4..5, 2, '~'), "      ~");
+    }
+    #[test]
+    fn classic() {
+        let mut display = String::new();
+        Position::new(0..0, 4..7)
+            .display_with(
+                &mut display,
+                &SourceFile::new("let foo;"),
+                &RenderConfig::classic(),
+            )
+            .unwrap();
+        assert_eq!(display, "  1 | let foo;\n    |     ~~~\n");
+    }
+    #[test]
+    fn colors() {
+        let source = SourceFile::new("let x;");
+        let pos = Position::new(0..0, 4..5);
+        let mark = Mark {
+            pos: &pos,
+            marker: '-',
+            style: Style::Secondary,
+            label: "x",
+        };
+        let config = RenderConfig::default().with_color(ColorChoice::Always);
+        assert_eq!(
+            render(&source, &config, 1, &[mark]),
+            "\x1b[1;34m1 |\x1b[0m let x;\n\x1b[1;34m  |\x1b[0m \x1b[1;34m    - x\x1b[0m\n"
+        );
+        let file = std::fs::File::open("Cargo.toml").unwrap();
+        assert_eq!(ColorChoice::Auto.for_stream(&file), ColorChoice::Never);
+        assert_eq!(ColorChoice::Always.for_stream(&file), ColorChoice::Always);
+    }
+    #[test]
+    fn context() {
+        let source = SourceFile::new("a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\n");
+        let (first, last) = (Position::new(1..1, 0..1), Position::new(9..9, 0..1));
+        let config = RenderConfig::default().with_context_lines(1);
+        assert_eq!(
+            render(&source, &config, 2, &[mark(&first, ""), mark(&last, "")]),
+            " 1 | a\n 2 | b\n   | ^\n 3 | c\n...\n 9 | i\n10 | j\n   | ^\n11 | k\n"
+        );
+    }
+    #[test]
+    fn multiline() {
+        let source = SourceFile::new("fn main() {\n    call(\n        x,\n    );\n}\n");
+        let body = Position::new(0..4, 0..1);
+        let call = Position::new(1..3, Range { start: 8, end: 5 });
+        let marks = [
+            Mark {
+                pos: &body,
+                marker: '-',
+                style: Style::Secondary,
+                label: "body",
+            },
+            Mark {
+                pos: &call,
+                marker: '^',
+                style: Style::Emphasis,
+                label: "call",
+            },
+        ];
+        assert_eq!(
+            render(&source, &RenderConfig::default(), 1, &marks),
+            "1 | /   fn main() {\n2 | |       call(\n  | |  _________^\n3 | | |         x,\n4 | | |     );\n  | | |_____^ call\n5 | |   }\n  | |___- body\n"
+        );
+    }
+    #[test]
+    fn multiline_inside_char() {
+        let mut display = String::new();
+        Position::new(0..1, 0..1)
+            .display(&mut display, "ab\n\u{e9}")
+            .unwrap();
+        assert_eq!(display, "  |\n1 | / ab\n2 | | \u{e9}\n  | |_^\n");
+    }
+    #[test]
+    fn long_lines() {
+        let line = format!("{}needle{}", "a".repeat(100), "b".repeat(100));
+        let source = SourceFile::new(line.as_str());
+        let pos = Position::new(0..0, 100..106);
+        let config = RenderConfig::default().with_max_width(Some(16));
+        assert_eq!(
+            render(&source, &config, 1, &[mark(&pos, "here")]),
+            "1 | ...aaaaaneedlebbbbb...\n  |         ^^^^^^ here\n"
+        );
+    }
+    #[test]
+    fn elided_span() {
+        let text = (1..=12).map(|n| format!("line {n}\n")).collect::<String>();
+        let source = SourceFile::new(text);
+        let pos = Position::new(0..11, 0..7);
+        let config = RenderConfig::default()
+            .with_max_span_lines(Some(4))
+            .with_separator(false);
+        let mut display = String::new();
+        pos.display_with(&mut display, &source, &config).unwrap();
+        assert_eq!(
+            display,
+            " 1 | / line 1\n 2 | | line 2\n...  |\n11 | | line 11\n12 | | line 12\n   | |_______^\n"
+        );
+        let config = config.with_span_edge_lines(0);
+        let mut display = String::new();
+        pos.display_with(&mut display, &source, &config).unwrap();
+        assert_eq!(
+            display,
+            " 1 | / line 1\n...  |\n12 | | line 12\n   | |_______^\n"
+        );
+    }
+    #[test]
+    fn points() {
+        let source = SourceFile::new("let x = ;\n");
+        let points = [
+            Position::point(0, 8),
+            Position::point(0, 9),
+            source.end_position(),
+        ];
+        let marks = points.each_ref().map(|pos| mark(pos, ""));
+        assert_eq!(
+            render(&source, &RenderConfig::default(), 1, &marks),
+            "1 | let x = ;\n  |         ^\n  |          ^\n2 |\n  | ^\n"
+        );
+    }
+}
@@ -0,0 +1,301 @@
+use std::path::Path;
+
+use crate::{
+    json::Json, ColumnUnit, Diagnostic, FileId, LabelStyle, Position, Severity, SourceMap,
+};
+
+/// exports diagnostics as a SARIF 2.1.0 log for code scanning tools
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sarif {
+    /// name of the tool producing the diagnostics
+    pub name: String,
+    pub version: Option<String>,
+    pub information_uri: Option<String>,
+}
+
+impl Sarif {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: None,
+            information_uri: None,
+        }
+    }
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+    pub fn with_information_uri(mut self, information_uri: impl Into<String>) -> Self {
+        self.information_uri = Some(information_uri.into());
+        self
+    }
+    /// a log with a single run holding one result per diagnostic
+    ///
+    /// diagnostic codes become rule ids, primary labels the locations,
+    /// secondary labels the related locations and suggestions the fixes
+    pub fn to_json(&self, diagnostics: &[Diagnostic], map: &SourceMap) -> String {
+        let mut rules: Vec<&str> = vec![];
+        for code in diagnostics
+            .iter()
+            .filter_map(|diagnostic| diagnostic.code.as_deref())
+        {
+            if !rules.contains(&code) {
+                rules.push(code);
+            }
+        }
+        let results = diagnostics
+            .iter()
+            .map(|diagnostic| result(diagnostic, &rules, map))
+            .collect();
+        let mut driver = vec![("name", self.name.as_str().into())];
+        if let Some(version) = &self.version {
+            driver.push(("version", version.as_str().into()));
+        }
+        if let Some(information_uri) = &self.information_uri {
+            driver.push(("informationUri", information_uri.as_str().into()));
+        }
+        let rules = rules
+            .iter()
+            .map(|&id| Json::Object(vec![("id", id.into())]))
+            .collect();
+        driver.push(("rules", Json::Array(rules)));
+        Json::Object(vec![
+            (
+                "$schema",
+                "https://json.schemastore.org/sarif-2.1.0.json".into(),
+            ),
+            ("version", "2.1.0".into()),
+            (
+                "runs",
+                Json::Array(vec![Json::Object(vec![
+                    ("tool", Json::Object(vec![("driver", Json::Object(driver))])),
+                    ("columnKind", "unicodeCodePoints".into()),
+                    ("results", Json::Array(results)),
+                ])]),
+            ),
+        ])
+        .to_string()
+    }
+}
+
+fn result(diagnostic: &Diagnostic, rules: &[&str], map: &SourceMap) -> Json {
+    let mut fields = vec![];
+    if let Some(code) = &diagnostic.code {
+        fields.push(("ruleId", code.as_str().into()));
+        let index = rules.iter().position(|rule| rule == code);
+        fields.push(("ruleIndex", index.into()));
+    }
+    let level = match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note | Severity::Help => "note",
+    };
+    fields.push(("level", level.into()));
+    let mut text = diagnostic.message.clone();
+    for note in &diagnostic.notes {
+        text.push_str(&format!("\nnote: {note}"));
+    }
+    for help in &diagnostic.help {
+        text.push_str(&format!("\nhelp: {help}"));
+    }
+    fields.push(("message", message(&text)));
+    let mut locations = vec![];
+    let mut related = vec![];
+    for label in &diagnostic.labels {
+        // files without a path have no artifact to point at
+        let mut location = vec![];
+        if let Some(artifact) = artifact(map, label.file) {
+            location.push((
+                "physicalLocation",
+                Json::Object(vec![
+                    ("artifactLocation", artifact),
+                    ("region", region(map, label.file, &label.pos)),
+                ]),
+            ));
+        }
+        if !label.message.is_empty() {
+            location.push(("message", message(&label.message)));
+        }
+        if location.is_empty() {
+            continue;
+        }
+        match label.style {
+            LabelStyle::Primary => locations.push(Json::Object(location)),
+            LabelStyle::Secondary => {
+                location.insert(0, ("id", related.len().into()));
+                related.push(Json::Object(location));
+            }
+        }
+    }
+    fields.push(("locations", Json::Array(locations)));
+    if !related.is_empty() {
+        fields.push(("relatedLocations", Json::Array(related)));
+    }
+    let fixes = diagnostic
+        .suggestions
+        .iter()
+        .filter_map(|suggestion| {
+            let mut changes: Vec<(FileId, Vec<Json>)> = vec![];
+            for replacement in &suggestion.replacements {
+                let json = Json::Object(vec![
+                    (
+                        "deletedRegion",
+                        region(map, replacement.file, &replacement.pos),
+                    ),
+                    (
+                        "insertedContent",
+                        Json::Object(vec![("text", replacement.text.as_str().into())]),
+                    ),
+                ]);
+                match changes
+                    .iter_mut()
+                    .find(|(file, _)| *file == replacement.file)
+                {
+                    Some((_, replacements)) => replacements.push(json),
+                    None => changes.push((replacement.file, vec![json])),
+                }
+            }
+            let changes = changes
+                .into_iter()
+                .filter_map(|(file, replacements)| {
+                    Some(Json::Object(vec![
+                        ("artifactLocation", artifact(map, file)?),
+                        ("replacements", Json::Array(replacements)),
+                    ]))
+                })
+                .collect::<Vec<Json>>();
+            (!changes.is_empty()).then(|| {
+                Json::Object(vec![
+                    ("description", message(&suggestion.message)),
+                    ("artifactChanges", Json::Array(changes)),
+                ])
+            })
+        })
+        .collect::<Vec<Json>>();
+    if !fixes.is_empty() {
+        fields.push(("fixes", Json::Array(fixes)));
+    }
+    Json::Object(fields)
+}
+fn message(text: &str) -> Json {
+    Json::Object(vec![("text", text.into())])
+}
+/// `None` for files without a path
+fn artifact(map: &SourceMap, file: FileId) -> Option<Json> {
+    let uri = uri(map.path(file)?);
+    Some(Json::Object(vec![("uri", uri.into())]))
+}
+/// one based lines and code point columns, with byte offsets if they are known
+fn region(map: &SourceMap, file: FileId, pos: &Position) -> Json {
+    let source = map.get(file);
+    let column = |ln: usize, col: usize| {
+        source
+            .and_then(|source| source.column(ln, col, ColumnUnit::Chars))
+            .unwrap_or(col)
+            + 1
+    };
+    let mut fields = vec![
+        ("startLine", (pos.ln.start + 1).into()),
+        ("startColumn", column(pos.ln.start, pos.col.start).into()),
+        ("endLine", (pos.ln.end + 1).into()),
+        ("endColumn", column(pos.ln.end, pos.col.end).into()),
+    ];
+    let bytes = source
+        .and_then(|source| source.byte_range(pos))
+        .or(pos.offset.clone());
+    if let Some(bytes) = bytes {
+        fields.push(("byteOffset", bytes.start.into()));
+        fields.push(("byteLength", bytes.len().into()));
+    }
+    Json::Object(fields)
+}
+/// `path` as a `file` URI if it's absolute, otherwise as a relative URI
+/// reference, percent-encoding what isn't allowed in one
+fn uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let bytes = path.as_bytes();
+    let drive = bytes.len() >= 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && bytes.get(2).is_none_or(|&byte| byte == b'/');
+    let (mut uri, rest) = if drive {
+        (format!("file:///{}", &path[..2]), &path[2..])
+    } else if path.starts_with("//") {
+        // a UNC path, `//server/share` already carries the authority
+        ("file:".to_string(), path.as_str())
+    } else if path.starts_with('/') {
+        ("file://".to_string(), path.as_str())
+    } else {
+        // `:` gets encoded too, so something like `a:b` isn't read as a scheme
+        (String::new(), path.as_str())
+    };
+    for byte in rest.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            byte => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Label, SourceFile, Suggestion};
+
+    use super::*;
+    #[test]
+    fn log() {
+        let mut map = SourceMap::new();
+        let file = map.add(Path::new("src/my file.txt").into(), "let x = y;\n");
+        let diagnostic = Diagnostic::warning("unused variable")
+            .with_code("unused")
+            .with_label(Label::primary(file, Position::new(0..0, 4..5)).with_message("never read"))
+            .with_label(Label::secondary(file, Position::new(0..0, 8..9)))
+            .with_suggestion(Suggestion::new("prefix it").with_replacement(
+                file,
+                Position::new(0..0, 4..5),
+                "_x",
+            ));
+        let sarif = Sarif::new("lint").with_version("0.1.0");
+        let json = sarif.to_json(&[diagnostic], &map);
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["version"], "2.1.0");
+        let run = &json["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "unused");
+        let result = &run["results"][0];
+        assert_eq!(result["level"], "warning");
+        assert_eq!(result["ruleIndex"], 0);
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/my%20file.txt");
+        assert_eq!(location["region"]["startColumn"], 5);
+        assert_eq!(location["region"]["byteLength"], 1);
+        assert_eq!(result["relatedLocations"][0]["id"], 0);
+        let change = &result["fixes"][0]["artifactChanges"][0];
+        assert_eq!(change["replacements"][0]["insertedContent"]["text"], "_x");
+    }
+    #[test]
+    fn unknown_paths() {
+        assert_eq!(uri(Path::new("C:\\x y")), "file:///C:/x%20y");
+        assert_eq!(uri(Path::new("/home/u/a.rs")), "file:///home/u/a.rs");
+        assert_eq!(uri(Path::new("src/a:b.rs")), "src/a%3Ab.rs");
+        let mut map = SourceMap::new();
+        let file = map.add_file(SourceFile::new("x"));
+        let diagnostic = Diagnostic::error("bad")
+            .with_label(Label::primary(file, Position::new(0..0, 0..1)).with_message("here"))
+            .with_label(Label::secondary(file, Position::new(0..0, 0..1)))
+            .with_suggestion(Suggestion::new("remove it").with_replacement(
+                file,
+                Position::new(0..0, 0..1),
+                "",
+            ));
+        let json = Sarif::new("lint").to_json(&[diagnostic], &map);
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let result = &json["runs"][0]["results"][0];
+        assert_eq!(result["locations"][0]["message"]["text"], "here");
+        assert!(result["locations"][0].get("physicalLocation").is_none());
+        assert!(result.get("relatedLocations").is_none());
+        assert!(result.get("fixes").is_none());
+    }
+}
@@ -0,0 +1,357 @@
+use std::{ops::Range, path::Path};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::Position;
+
+/// what a column counts
+///
+/// `Position` columns are always byte based, these units are for converting
+/// them for tools that count differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ColumnUnit {
+    #[default]
+    Bytes,
+    Chars,
+    Utf16,
+    Graphemes,
+}
+
+/// which characters end a line
+///
+/// shared by `SourceFile`, `Cursor` and rendering so they always agree on
+/// line numbers, `\r\n` is always a single line break
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LineEndings {
+    /// `\n` and `\r\n`, a lone `\r` is part of the line like with `str::lines`
+    Lf,
+    /// `\n`, `\r\n` and a lone `\r` as in old Mac files
+    #[default]
+    Ascii,
+    /// like `Ascii` plus the unicode line and paragraph separators U+2028 and U+2029
+    Unicode,
+}
+
+/// source text with an index of where each line starts
+///
+/// the index is built once, so looking up lines and converting between byte
+/// offsets and lines/columns doesn't rescan the text
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceFile {
+    path: Option<Box<Path>>,
+    text: String,
+    line_endings: LineEndings,
+    /// byte ranges of the lines without their line breaks
+    lines: Vec<Range<usize>>,
+}
+
+impl SourceFile {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self::with_line_endings(text, LineEndings::default())
+    }
+    /// creates a `SourceFile` which splits lines at `line_endings`
+    pub fn with_line_endings(text: impl Into<String>, line_endings: LineEndings) -> Self {
+        let text = text.into();
+        let mut lines = vec![];
+        let mut start = 0;
+        for line_break in line_endings.breaks(&text) {
+            lines.push(start..line_break.start);
+            start = line_break.end;
+        }
+        lines.push(start..text.len());
+        Self {
+            path: None,
+            text,
+            line_endings,
+            lines,
+        }
+    }
+    /// creates a `SourceFile` which knows the path it was read from
+    pub fn with_path(path: Box<Path>, text: impl Into<String>) -> Self {
+        Self::with_path_and_line_endings(path, text, LineEndings::default())
+    }
+    /// creates a `SourceFile` which knows the path it was read from
+    /// and splits lines at `line_endings`
+    pub fn with_path_and_line_endings(
+        path: Box<Path>,
+        text: impl Into<String>,
+        line_endings: LineEndings,
+    ) -> Self {
+        Self {
+            path: Some(path),
+            ..Self::with_line_endings(text, line_endings)
+        }
+    }
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    pub fn line_endings(&self) -> LineEndings {
+        self.line_endings
+    }
+    /// number of lines, a trailing line terminator starts an empty last line
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+    /// byte offset at which line `ln` starts
+    pub fn line_start(&self, ln: usize) -> Option<usize> {
+        self.lines.get(ln).map(|line| line.start)
+    }
+    /// byte range of line `ln` without its line terminator
+    pub fn line_range(&self, ln: usize) -> Option<Range<usize>> {
+        self.lines.get(ln).cloned()
+    }
+    /// line `ln` without its line terminator
+    pub fn line(&self, ln: usize) -> Option<&str> {
+        self.line_range(ln).map(|range| &self.text[range])
+    }
+    /// line and byte column of `offset`, clamped to the end of the text
+    pub fn location(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.text.len());
+        let ln = self.lines.partition_point(|line| line.start <= offset) - 1;
+        (ln, offset - self.lines[ln].start)
+    }
+    /// byte offset of the byte column `col` on line `ln`,
+    /// `None` if the column is past the line and its terminator
+    pub fn offset(&self, ln: usize, col: usize) -> Option<usize> {
+        let start = self.line_start(ln)?;
+        let end = self.line_start(ln + 1).unwrap_or(self.text.len());
+        (col <= end - start).then_some(start + col)
+    }
+    /// converts the byte column `col` on line `ln` into `unit`
+    pub fn column(&self, ln: usize, col: usize, unit: ColumnUnit) -> Option<usize> {
+        let line = self.line(ln)?;
+        Some(unit.measure(line.get(..col)?))
+    }
+    /// converts the column `col` on line `ln` counted in `unit` into a byte column
+    pub fn byte_column(&self, ln: usize, col: usize, unit: ColumnUnit) -> Option<usize> {
+        unit.byte_offset(self.line(ln)?, col)
+    }
+    /// absolute byte offsets covered by `pos`, computed from its lines and
+    /// columns if it doesn't store them
+    pub fn byte_range(&self, pos: &Position) -> Option<Range<usize>> {
+        if let Some(offset) = &pos.offset {
+            return Some(offset.clone());
+        }
+        let start = self.offset(pos.ln.start, pos.col.start)?;
+        let end = self.offset(pos.ln.end, pos.col.end)?;
+        (start <= end).then_some(start..end)
+    }
+    /// zero width `Position` at the end of the text
+    pub fn end_position(&self) -> Position {
+        self.position(self.text.len()..self.text.len())
+    }
+    /// creates a `Position` covering the byte range `offset`
+    pub fn position(&self, offset: Range<usize>) -> Position {
+        let (ln_start, col_start) = self.location(offset.start);
+        let (ln_end, col_end) = self.location(offset.end);
+        Position {
+            ln: ln_start..ln_end,
+            col: col_start..col_end,
+            offset: Some(offset),
+        }
+    }
+}
+
+impl LineEndings {
+    /// whether `c` ends a line when it's followed by `next`
+    pub fn ends_line(self, c: char, next: Option<char>) -> bool {
+        match c {
+            '\n' => true,
+            '\r' => self != Self::Lf && next != Some('\n'),
+            '\u{2028}' | '\u{2029}' => self == Self::Unicode,
+            _ => false,
+        }
+    }
+    /// byte ranges of the line breaks in `text`
+    pub fn breaks(self, text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+        let bytes = text.as_bytes();
+        text.char_indices().filter_map(move |(idx, c)| {
+            let next = text[idx + c.len_utf8()..].chars().next();
+            if !self.ends_line(c, next) {
+                return None;
+            }
+            let start = if c == '\n' && idx > 0 && bytes[idx - 1] == b'\r' {
+                idx - 1
+            } else {
+                idx
+            };
+            Some(start..idx + c.len_utf8())
+        })
+    }
+}
+impl ColumnUnit {
+    /// length of `text` in this unit
+    pub fn measure(self, text: &str) -> usize {
+        match self {
+            Self::Bytes => text.len(),
+            Self::Chars => text.chars().count(),
+            Self::Utf16 => text.encode_utf16().count(),
+            Self::Graphemes => text.graphemes(true).count(),
+        }
+    }
+    /// byte offset in `line` of the column `col` counted in this unit,
+    /// `None` if the line is shorter or the column splits a character
+    pub fn byte_offset(self, line: &str, col: usize) -> Option<usize> {
+        let mut count = 0;
+        let mut boundaries: Box<dyn Iterator<Item = (usize, &str)>> = match self {
+            Self::Bytes => return line.is_char_boundary(col).then_some(col),
+            Self::Chars | Self::Utf16 => Box::new(
+                line.char_indices()
+                    .map(|(idx, c)| (idx, &line[idx..idx + c.len_utf8()])),
+            ),
+            Self::Graphemes => Box::new(line.grapheme_indices(true)),
+        };
+        loop {
+            if count == col {
+                return Some(boundaries.next().map_or(line.len(), |(idx, _)| idx));
+            }
+            let (_, part) = boundaries.next()?;
+            count += self.measure(part);
+            if count > col {
+                return None;
+            }
+        }
+    }
+}
+
+/// compact handle to a file in a `SourceMap`
+///
+/// with the `serde` feature it's (de)serialized as its index, a plain number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct FileId(u32);
+
+/// registry of source files, handing out a `FileId` for each added file
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl FileId {
+    /// index of the file in its `SourceMap`
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// adds a file with the default line endings and returns its id,
+    /// `add_file` takes a `SourceFile` with any others
+    pub fn add(&mut self, path: Box<Path>, text: impl Into<String>) -> FileId {
+        self.add_file(SourceFile::with_path(path, text))
+    }
+    /// adds an already built `SourceFile` and returns its id
+    pub fn add_file(&mut self, file: SourceFile) -> FileId {
+        let id = FileId(u32::try_from(self.files.len()).expect("too many files in source map"));
+        self.files.push(file);
+        id
+    }
+    pub fn get(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.index())
+    }
+    pub fn path(&self, id: FileId) -> Option<&Path> {
+        self.get(id)?.path()
+    }
+    pub fn text(&self, id: FileId) -> Option<&str> {
+        self.get(id).map(SourceFile::text)
+    }
+    /// id of the first file added with `path`
+    pub fn find(&self, path: &Path) -> Option<FileId> {
+        self.files
+            .iter()
+            .position(|file| file.path() == Some(path))
+            .map(|idx| FileId(idx as u32))
+    }
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+    /// all files with their ids, in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(idx, file)| (FileId(idx as u32), file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn lookups() {
+        let source = SourceFile::new("fn main() {\r\n    42\n}\n");
+        assert_eq!(source.line_count(), 4);
+        assert_eq!(source.line(0), Some("fn main() {"));
+        assert_eq!(source.line(1), Some("    42"));
+        assert_eq!(source.line(3), Some(""));
+        assert_eq!(source.line(4), None);
+        assert_eq!(source.location(17), (1, 4));
+        assert_eq!(source.offset(1, 4), Some(17));
+        assert_eq!(source.offset(0, 13), Some(13));
+        assert_eq!(source.offset(0, 14), None);
+        let short = SourceFile::new("ab\ncd");
+        assert_eq!(Position::new(0..0, 0..5).slice_in(&short), None);
+        assert_eq!(source.location(100), (3, 0));
+        assert_eq!(source.position(17..19).ln, 1..1);
+    }
+    #[test]
+    fn line_endings() {
+        let text = "a\r\nb\rc\u{2028}d\n";
+        let lf = SourceFile::with_line_endings(text, LineEndings::Lf);
+        assert_eq!(lf.line_count(), 3);
+        assert_eq!(lf.line(1), Some("b\rc\u{2028}d"));
+        let ascii = SourceFile::new(text);
+        assert_eq!(ascii.line_count(), 4);
+        assert_eq!(ascii.line(2), Some("c\u{2028}d"));
+        let unicode = SourceFile::with_line_endings(text, LineEndings::Unicode);
+        assert_eq!(unicode.line_count(), 5);
+        assert_eq!(unicode.line(0), Some("a"));
+        assert_eq!(unicode.line(3), Some("d"));
+        assert_eq!(unicode.location(text.find('d').unwrap()), (3, 0));
+    }
+    #[test]
+    fn columns() {
+        let source = SourceFile::new("a\u{e9}\u{1f600}e\u{301}x");
+        let x = source.line(0).unwrap().len() - 1;
+        assert_eq!(source.column(0, x, ColumnUnit::Bytes), Some(10));
+        assert_eq!(source.column(0, x, ColumnUnit::Chars), Some(5));
+        assert_eq!(source.column(0, x, ColumnUnit::Utf16), Some(6));
+        assert_eq!(source.column(0, x, ColumnUnit::Graphemes), Some(4));
+        assert_eq!(source.byte_column(0, 6, ColumnUnit::Utf16), Some(x));
+        assert_eq!(source.byte_column(0, 3, ColumnUnit::Utf16), None);
+        assert_eq!(source.byte_column(0, 4, ColumnUnit::Graphemes), Some(x));
+        assert_eq!(source.byte_column(0, 5, ColumnUnit::Graphemes), Some(x + 1));
+        assert_eq!(source.byte_column(0, 6, ColumnUnit::Graphemes), None);
+    }
+    #[test]
+    fn source_map() {
+        let mut map = SourceMap::new();
+        let a = map.add(Path::new("a.txt").into(), "a");
+        let b = map.add(Path::new("b.txt").into(), "b\nb");
+        assert_ne!(a, b);
+        assert_eq!(map.text(b), Some("b\nb"));
+        assert_eq!(map.path(a), Some(Path::new("a.txt")));
+        assert_eq!(map.find(Path::new("b.txt")), Some(b));
+        assert_eq!(map.get(b).map(SourceFile::line_count), Some(2));
+        let c = map.add_file(SourceFile::with_path_and_line_endings(
+            Path::new("c.txt").into(),
+            "c\rc",
+            LineEndings::Lf,
+        ));
+        assert_eq!(map.path(c), Some(Path::new("c.txt")));
+        assert_eq!(map.get(c).map(SourceFile::line_count), Some(1));
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{FileLocated, Located, PathLocated, Position};
+
+/// anything with a span in the source
+///
+/// collections like `Option`, `Vec`, slices and tuples return the span covering
+/// all of their items, skipping the ones without any
+pub trait Spanned {
+    fn pos(&self) -> Position;
+    /// the span, or `None` if there's nothing to cover (like an empty `Vec`)
+    fn try_pos(&self) -> Option<Position> {
+        Some(self.pos())
+    }
+}
+
+/// merges the spans of all items that have one
+fn covering<'a, T: Spanned + 'a>(items: impl IntoIterator<Item = &'a T>) -> Option<Position> {
+    items
+        .into_iter()
+        .filter_map(Spanned::try_pos)
+        .reduce(|pos, other| pos.merge(&other))
+}
+
+impl Spanned for Position {
+    fn pos(&self) -> Position {
+        self.clone()
+    }
+}
+impl<T> Spanned for Located<T> {
+    fn pos(&self) -> Position {
+        self.pos.clone()
+    }
+}
+impl<T, P> Spanned for PathLocated<T, P> {
+    fn pos(&self) -> Position {
+        self.pos.clone()
+    }
+}
+impl<T> Spanned for FileLocated<T> {
+    fn pos(&self) -> Position {
+        self.pos.clone()
+    }
+}
+impl<T: Spanned + ?Sized> Spanned for &T {
+    fn pos(&self) -> Position {
+        (**self).pos()
+    }
+    fn try_pos(&self) -> Option<Position> {
+        (**self).try_pos()
+    }
+}
+impl<T: Spanned + ?Sized> Spanned for Box<T> {
+    fn pos(&self) -> Position {
+        (**self).pos()
+    }
+    fn try_pos(&self) -> Option<Position> {
+        (**self).try_pos()
+    }
+}
+/// `Position::default()` for `None`
+impl<T: Spanned> Spanned for Option<T> {
+    fn pos(&self) -> Position {
+        self.try_pos().unwrap_or_default()
+    }
+    fn try_pos(&self) -> Option<Position> {
+        self.as_ref().and_then(Spanned::try_pos)
+    }
+}
+/// `Position::default()` if empty
+impl<T: Spanned> Spanned for [T] {
+    fn pos(&self) -> Position {
+        self.try_pos().unwrap_or_default()
+    }
+    fn try_pos(&self) -> Option<Position> {
+        covering(self)
+    }
+}
+/// `Position::default()` if empty
+impl<T: Spanned> Spanned for Vec<T> {
+    fn pos(&self) -> Position {
+        self.as_slice().pos()
+    }
+    fn try_pos(&self) -> Option<Position> {
+        self.as_slice().try_pos()
+    }
+}
+
+macro_rules! tuples {
+    ($($name:ident)+) => {
+        #[allow(non_snake_case)]
+        impl<$($name: Spanned),+> Spanned for ($($name,)+) {
+            fn pos(&self) -> Position {
+                self.try_pos().unwrap_or_default()
+            }
+            fn try_pos(&self) -> Option<Position> {
+                let ($($name,)+) = self;
+                [$($name.try_pos()),+]
+                    .into_iter()
+                    .flatten()
+                    .reduce(|pos, other| pos.merge(&other))
+            }
+        }
+    };
+}
+tuples!(A);
+tuples!(A B);
+tuples!(A B C);
+tuples!(A B C D);
+tuples!(A B C D E);
+tuples!(A B C D E F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(spanned: impl Spanned) -> Position {
+        spanned.pos()
+    }
+
+    #[test]
+    fn spans() {
+        let a = Located::new("a", Position::new(0..0, 2..3).with_offset(2..3));
+        let b = Located::new("b", Position::new(1..1, 0..4).with_offset(6..10));
+        let covering = Position::new(0..1, 2..4).with_offset(2..10);
+        assert_eq!(span(&a), a.pos);
+        assert_eq!(span(Box::new(b.clone())), b.pos);
+        assert_eq!(span(vec![b.clone(), a.clone()]), covering);
+        assert_eq!(span((&a, Some(&b))), covering);
+        assert_eq!(span((&a, None::<&Position>)), a.pos);
+        assert_eq!(Vec::<Position>::new().try_pos(), None);
+        assert_eq!(span(&[None, Some(&b)][..]), b.pos);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derive() {
+        #[derive(crate::Spanned)]
+        struct Call {
+            name: Located<&'static str>,
+            args: Vec<Expr>,
+        }
+        #[derive(crate::Spanned)]
+        enum Expr {
+            Number(Located<usize>),
+            Call(Box<Call>),
+            Binary {
+                #[span]
+                pos: Position,
+                left: Box<Expr>,
+                #[span(skip)]
+                op: char,
+                right: Box<Expr>,
+            },
+            Missing,
+        }
+        #[derive(crate::Spanned)]
+        struct Wrapper<T, U> {
+            inner: T,
+            #[span(skip)]
+            #[allow(dead_code)]
+            extra: U,
+        }
+
+        let number =
+            |n: usize, col: usize| Expr::Number(Located::new(n, Position::new(0..0, col..col + 1)));
+        let call = Call {
+            name: Located::new("f", Position::new(0..0, 0..1)),
+            args: vec![number(1, 2), Expr::Missing, number(2, 5)],
+        };
+        assert_eq!(call.pos(), Position::new(0..0, 0..6));
+        let binary = Expr::Binary {
+            pos: Position::new(1..1, 0..5),
+            left: Box::new(number(1, 0)),
+            op: '+',
+            right: Box::new(Expr::Call(Box::new(call))),
+        };
+        assert_eq!(binary.pos(), Position::new(1..1, 0..5));
+        assert_eq!(Expr::Missing.try_pos(), None);
+        let wrapper = Wrapper {
+            inner: number(3, 4),
+            extra: 'x',
+        };
+        assert_eq!(wrapper.pos(), Position::new(0..0, 4..5));
+
+        #[derive(crate::Spanned)]
+        struct Node<T> {
+            value: Located<T>,
+            children: Vec<Node<T>>,
+        }
+        let node = Node {
+            value: Located::new(String::from("root"), Position::new(0..0, 0..4)),
+            children: vec![Node {
+                value: Located::new(String::from("leaf"), Position::new(1..1, 2..6)),
+                children: vec![],
+            }],
+        };
+        assert_eq!(node.pos(), Position::new(0..1, 0..6));
+
+        #[derive(crate::Spanned)]
+        enum Item<T> {
+            Lit(Located<T>),
+            Block(Box<Stmt<T>>),
+        }
+        #[derive(crate::Spanned)]
+        enum Stmt<T> {
+            Item(Item<T>),
+            Many(Vec<Stmt<T>>),
+        }
+        let lit = |col: usize| Item::Lit(Located::new(0u8, Position::new(0..0, col..col + 1)));
+        let block = Item::Block(Box::new(Stmt::Many(vec![
+            Stmt::Item(lit(1)),
+            Stmt::Item(lit(7)),
+        ])));
+        assert_eq!(block.pos(), Position::new(0..0, 1..8));
+
+        #[derive(crate::Spanned)]
+        #[span(bound = "T: Spanned")]
+        struct Boxed<T> {
+            inner: Box<T>,
+        }
+        let boxed = Boxed {
+            inner: Box::new(Position::new(2..2, 0..1)),
+        };
+        assert_eq!(boxed.pos(), Position::new(2..2, 0..1));
+    }
+}